@@ -0,0 +1,51 @@
+//! `sqlx-d1` CLI, filling the `.sqlx` query caches by a temporary database where
+//! `migrations` are applied, without `cargo sqlx` nor `DATABASE_URL`.
+
+mod prepare;
+
+use std::process::ExitCode;
+
+type Result<T> = std::result::Result<T, Box<dyn std::error::Error>>;
+
+const USAGE: &str = "\
+Usage: sqlx-d1 prepare [--check] [--migrations <DIR>] [-- <CARGO CHECK ARGS>...]
+
+Commands:
+  prepare  Rebuild the workspace against a temporary database where the migrations
+           are applied to fill `.sqlx`
+
+Options:
+  --check             Rebuild the workspace into a temporary directory and verify that
+                      `.sqlx` is up to date, without modifying it
+  --migrations <DIR>  Directory of the migrations [default: `migrations` found in the
+                      same way as `.sqlx`]
+  -h, --help          Print help
+";
+
+fn main() -> ExitCode {
+    let args = std::env::args().skip(1).collect::<Vec<_>>();
+
+    let result = match args.split_first() {
+        Some((command, rest)) if command == "prepare" => match prepare::Args::parse(rest) {
+            Ok(Some(args)) => prepare::run(args),
+            Ok(None) => {
+                print!("{USAGE}");
+                Ok(())
+            }
+            Err(e) => Err(e),
+        },
+        Some((help, _)) if help == "-h" || help == "--help" => {
+            print!("{USAGE}");
+            Ok(())
+        }
+        _ => Err(format!("expected a command\n\n{USAGE}").into()),
+    };
+
+    match result {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(e) => {
+            eprintln!("error: {e}");
+            ExitCode::FAILURE
+        }
+    }
+}
@@ -0,0 +1,347 @@
+//! ref: <https://github.com/launchbadge/sqlx/blob/6651d2df72586519708147d96e1ec1054a898c1e/sqlx-cli/src/prepare.rs>
+
+use crate::Result;
+use sqlx_d1_core::{D1ConnectOptions, D1Migration, D1Migrator};
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+pub(crate) struct Args {
+    check: bool,
+    migrations: Option<PathBuf>,
+    cargo_args: Vec<String>,
+}
+
+impl Args {
+    /// `None` for `--help`.
+    pub(crate) fn parse(args: &[String]) -> Result<Option<Self>> {
+        let mut this = Args {
+            check: false,
+            migrations: None,
+            cargo_args: Vec::new(),
+        };
+
+        let mut args = args.iter();
+        while let Some(arg) = args.next() {
+            match arg.as_str() {
+                "--check" => this.check = true,
+                "--migrations" => {
+                    this.migrations = Some(
+                        args.next()
+                            .ok_or("`--migrations` requires a directory")?
+                            .into(),
+                    );
+                }
+                "-h" | "--help" => return Ok(None),
+                "--" => this.cargo_args.extend(args.by_ref().cloned()),
+                unknown => return Err(format!("unexpected argument `{unknown}`").into()),
+            }
+        }
+
+        Ok(Some(this))
+    }
+}
+
+pub(crate) fn run(args: Args) -> Result<()> {
+    let workspace = Workspace::load()?;
+    let dot_sqlx_dir = workspace.dot_sqlx_dir()?;
+    if args.check && !dot_sqlx_dir.is_dir() {
+        return Err("`.sqlx` directory is not found. Run `sqlx-d1 prepare` first.".into());
+    }
+
+    /* `.sqlx` is left as it is until the build succeeds */
+    let prepared_dir = workspace.target_directory.join("sqlx-d1-prepare");
+    if prepared_dir.exists() {
+        std::fs::remove_dir_all(&prepared_dir)?;
+    }
+    std::fs::create_dir_all(&prepared_dir)?;
+
+    let result = (|| {
+        let migrations_dir = match args.migrations {
+            Some(dir) => dir,
+            None => workspace.migrations_dir()?,
+        };
+        let database = prepared_dir.join("database.sqlite");
+        migrate(&migrations_dir, &database)?;
+        prepare(&workspace, &args.cargo_args, &database, &prepared_dir)?;
+        if args.check {
+            check(&prepared_dir, &dot_sqlx_dir)
+        } else {
+            replace(&prepared_dir, &dot_sqlx_dir)
+        }
+    })();
+    /* cleanup doesn't hide the result */
+    let _ = std::fs::remove_dir_all(&prepared_dir);
+    result
+}
+
+struct Workspace {
+    root: PathBuf,
+    target_directory: PathBuf,
+    /// packages depending on `sqlx-d1`, whose queries are to be cached
+    packages: Vec<Package>,
+}
+
+#[derive(serde::Deserialize)]
+struct Package {
+    name: String,
+    manifest_path: PathBuf,
+    dependencies: Vec<Dependency>,
+}
+
+#[derive(serde::Deserialize)]
+struct Dependency {
+    name: String,
+}
+
+impl Workspace {
+    fn load() -> Result<Self> {
+        #[derive(serde::Deserialize)]
+        struct CargoMetadata {
+            workspace_root: PathBuf,
+            target_directory: PathBuf,
+            packages: Vec<Package>,
+        }
+
+        let output = cargo()
+            .args(["metadata", "--format-version=1", "--no-deps"])
+            .output()?;
+        if !output.status.success() {
+            return Err(format!(
+                "`cargo metadata` failed: {}",
+                String::from_utf8_lossy(&output.stderr)
+            )
+            .into());
+        }
+        let metadata = serde_json::from_slice::<CargoMetadata>(&output.stdout)?;
+
+        Ok(Self {
+            root: metadata.workspace_root,
+            target_directory: metadata.target_directory,
+            packages: metadata
+                .packages
+                .into_iter()
+                .filter(|p| p.dependencies.iter().any(|d| d.name == "sqlx-d1"))
+                .collect(),
+        })
+    }
+
+    /// Where the query macros search: the package root of each package
+    /// depending on `sqlx-d1`, and then the workspace root.
+    fn parent_candidates(&self) -> Vec<&Path> {
+        let mut candidates = Vec::new();
+        for parent in self
+            .packages
+            .iter()
+            .filter_map(|p| p.manifest_path.parent())
+            .chain([&*self.root])
+        {
+            if !candidates.contains(&parent) {
+                candidates.push(parent);
+            }
+        }
+        candidates
+    }
+
+    /// `migrations` directory as wrangler's default `migrations_dir`, searched
+    /// in the same order as `.sqlx`.
+    fn migrations_dir(&self) -> Result<PathBuf> {
+        self.parent_candidates()
+            .into_iter()
+            .map(|parent| parent.join("migrations"))
+            .find(|candidate| candidate.is_dir())
+            .ok_or_else(|| {
+                "`migrations` directory is not found. Specify it by `--migrations <DIR>`.".into()
+            })
+    }
+
+    /// Same as `Location::dot_sqlx_dir` of the query macros, or `.sqlx` to be
+    /// created at the workspace root.
+    fn dot_sqlx_dir(&self) -> Result<PathBuf> {
+        let mut dot_sqlx_dirs = self
+            .parent_candidates()
+            .into_iter()
+            .map(|parent| parent.join(".sqlx"))
+            .filter(|candidate| candidate.is_dir())
+            .collect::<Vec<_>>();
+
+        match dot_sqlx_dirs.len() {
+            0 => Ok(self.root.join(".sqlx")),
+            1 => Ok(dot_sqlx_dirs.pop().unwrap()),
+            _ => Err(format!(
+                "Multiple `.sqlx` directories are found: `{}`. \
+                Sorry, sqlx-d1 prepare only supports single `.sqlx` directory now.",
+                dot_sqlx_dirs
+                    .iter()
+                    .map(|dir| dir.display().to_string())
+                    .collect::<Vec<_>>()
+                    .join("`, `")
+            )
+            .into()),
+        }
+    }
+}
+
+fn cargo() -> Command {
+    Command::new(std::env::var("CARGO").unwrap_or_else(|_| String::from("cargo")))
+}
+
+fn query_cache_files(dir: &Path) -> Result<Vec<PathBuf>> {
+    let mut files = std::fs::read_dir(dir)?
+        .filter_map(|r| r.as_ref().ok().map(|e| e.path()))
+        .filter(|p| {
+            p.file_name()
+                .and_then(|name| name.to_str())
+                .is_some_and(|name| name.starts_with("query-") && name.ends_with(".json"))
+        })
+        .collect::<Vec<_>>();
+    files.sort();
+    Ok(files)
+}
+
+/// Query caches in `dir` by the file names.
+fn query_caches(dir: &Path) -> Result<BTreeMap<String, serde_json::Value>> {
+    let mut caches = BTreeMap::new();
+    for file in query_cache_files(dir)? {
+        let name = file
+            .file_name()
+            .unwrap_or_default()
+            .to_string_lossy()
+            .into_owned();
+        let cache = serde_json::from_slice(&std::fs::read(&file)?)
+            .map_err(|e| format!("failed to parse `{}`: {e}", file.display()))?;
+        caches.insert(name, cache);
+    }
+    Ok(caches)
+}
+
+/// Apply the migrations in `migrations_dir` into a fresh SQLite database
+/// at `database`, standing in for miniflare's D1 emulator.
+fn migrate(migrations_dir: &Path, database: &Path) -> Result<()> {
+    let mut migrations = Vec::new();
+    for entry in std::fs::read_dir(migrations_dir).map_err(|e| {
+        format!(
+            "failed to read migrations directory `{}`: {e}",
+            migrations_dir.display()
+        )
+    })? {
+        let path = entry?.path();
+        if path.is_file() && path.extension().is_some_and(|x| x == "sql") {
+            let name = path
+                .file_name()
+                .map(|name| name.to_string_lossy().into_owned())
+                .unwrap_or_default();
+            migrations.push(D1Migration::new(name, std::fs::read_to_string(&path)?));
+        }
+    }
+
+    futures_lite::future::block_on(async {
+        let conn = D1ConnectOptions::sqlite(database).connect().await?;
+        D1Migrator::new(migrations)
+            .run(&conn)
+            .await
+            .map_err(|e| format!("failed to apply migrations: {e}"))?;
+        Ok(())
+    })
+}
+
+/// Rebuild the packages depending on `sqlx-d1`, where the query macros
+/// describe the queries by `database` and cache them in `prepared_dir`.
+fn prepare(
+    workspace: &Workspace,
+    cargo_args: &[String],
+    database: &Path,
+    prepared_dir: &Path,
+) -> Result<()> {
+    /* the query macros are expanded only when the packages are recompiled */
+    for Package { name, .. } in &workspace.packages {
+        let status = cargo().args(["clean", "--package", name]).status()?;
+        if !status.success() {
+            return Err(format!("`cargo clean --package {name}` failed").into());
+        }
+    }
+    let status = cargo()
+        .args(["check", "--workspace"])
+        .args(cargo_args)
+        .env("SQLX_D1_DATABASE", database)
+        .env("SQLX_OFFLINE_DIR", prepared_dir)
+        .status()?;
+    if !status.success() {
+        return Err("`cargo check` failed, `.sqlx` is not modified".into());
+    }
+
+    Ok(())
+}
+
+/// Replace the query caches in `dot_sqlx_dir` with the prepared ones.
+fn replace(prepared_dir: &Path, dot_sqlx_dir: &Path) -> Result<()> {
+    std::fs::create_dir_all(dot_sqlx_dir)?;
+
+    /* caches of removed queries are not overwritten */
+    for file in query_cache_files(dot_sqlx_dir)? {
+        std::fs::remove_file(file)?;
+    }
+    let files = query_cache_files(prepared_dir)?;
+    for file in &files {
+        std::fs::copy(
+            file,
+            dot_sqlx_dir.join(file.file_name().unwrap_or_default()),
+        )?;
+    }
+
+    println!(
+        "{} queries are cached in `{}`",
+        files.len(),
+        dot_sqlx_dir.display()
+    );
+    Ok(())
+}
+
+/// Check that the query caches in `dot_sqlx_dir` are exactly the prepared ones,
+/// as `cargo sqlx prepare --check` does.
+fn check(prepared_dir: &Path, dot_sqlx_dir: &Path) -> Result<()> {
+    let prepared = query_caches(prepared_dir)?;
+    let cached = query_caches(dot_sqlx_dir)?;
+
+    let query_of = |cache: &serde_json::Value| {
+        cache
+            .get("query")
+            .and_then(|query| query.as_str())
+            .unwrap_or("( no query recorded, cached by an older version )")
+            .to_owned()
+    };
+    let mut problems = Vec::new();
+    for (name, cache) in &prepared {
+        match cached.get(name) {
+            None => problems.push(format!("`{name}`: not cached\n    {}", query_of(cache))),
+            Some(old) if old != cache => problems.push(format!(
+                "`{name}`: cached differently\n    {}",
+                query_of(cache)
+            )),
+            Some(_) => (),
+        }
+    }
+    for (name, cache) in &cached {
+        if !prepared.contains_key(name) {
+            problems.push(format!(
+                "`{name}`: cached for a query no longer used\n    {}",
+                query_of(cache)
+            ));
+        }
+    }
+
+    if problems.is_empty() {
+        println!("{} cached queries are up to date", cached.len());
+        Ok(())
+    } else {
+        for problem in &problems {
+            eprintln!("{problem}");
+        }
+        Err(format!(
+            "{} query caches in `{}` are stale. Run `sqlx-d1 prepare` to update them.",
+            problems.len(),
+            dot_sqlx_dir.display()
+        )
+        .into())
+    }
+}
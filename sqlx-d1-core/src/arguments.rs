@@ -31,11 +31,11 @@ impl<'q> sqlx_core::arguments::IntoArguments<'q, crate::D1> for D1Arguments {
     }
 }
 
-impl AsRef<[worker::wasm_bindgen::JsValue]> for D1Arguments {
-    fn as_ref(&self) -> &[worker::wasm_bindgen::JsValue] {
+impl AsRef<[crate::value::RawValue]> for D1Arguments {
+    fn as_ref(&self) -> &[crate::value::RawValue] {
         let this: &[crate::value::D1Value] = self.0.as_slice();
 
-        /* SAFETY: `D1Value` is newtype of `JsValue` */
+        /* SAFETY: `D1Value` is newtype of `RawValue` */
         unsafe { std::mem::transmute(this) }
     }
 }
@@ -0,0 +1,174 @@
+//! ref: <https://developers.cloudflare.com/d1/worker-api/d1-database/#batch>
+
+use crate::{D1, D1Connection, arguments::D1Arguments, query_result::D1QueryResult, row::D1Row};
+use sqlx_core::query::Query;
+
+#[cfg(target_arch = "wasm32")]
+use {
+    crate::{error::D1Error, ffi::D1Binding, row::RawResultSet},
+    worker::{js_sys, wasm_bindgen::JsCast, wasm_bindgen_futures::JsFuture},
+};
+
+/// Result of each statement executed by [`D1Connection::batch`].
+///
+/// NOTE: D1's `batch` returns rows as objects, so duplicate column names
+/// (like `SELECT a.id, b.id`) are collapsed into one in `rows`.
+pub struct D1BatchResult {
+    pub rows: Vec<D1Row>,
+    pub query_result: D1QueryResult,
+}
+
+impl D1Connection {
+    /// Execute the queries as a batch.
+    ///
+    /// Batched statements are SQL transactions: if any of them fails,
+    /// the entire sequence is aborted or rolled back, and the failure is
+    /// returned as a single error.
+    ///
+    /// ## Example
+    ///
+    /// ```rust,ignore
+    /// let results = conn
+    ///     .batch([
+    ///         sqlx_d1::query("INSERT INTO orders (item_id, amount) VALUES (?, ?)")
+    ///             .bind(item_id)
+    ///             .bind(amount),
+    ///         sqlx_d1::query("UPDATE items SET stock = stock - ? WHERE id = ?")
+    ///             .bind(amount)
+    ///             .bind(item_id),
+    ///     ])
+    ///     .await?;
+    /// ```
+    ///
+    /// Queries made from a prepared statement reuse it without re-preparing:
+    ///
+    /// ```rust,ignore
+    /// use sqlx_d1::sqlx_core::{executor::Executor, statement::Statement};
+    ///
+    /// let insert = (&conn).prepare("INSERT INTO tags (name) VALUES (?)").await?;
+    /// conn.batch(tags.iter().map(|tag| insert.query().bind(tag))).await?;
+    /// ```
+    pub async fn batch<'q>(
+        &self,
+        queries: impl IntoIterator<Item = Query<'q, D1, D1Arguments>>,
+    ) -> Result<Vec<D1BatchResult>, sqlx_core::Error> {
+        self.ensure_open()?;
+        self.ensure_no_transaction("batch")?;
+        #[cfg(not(target_arch = "wasm32"))]
+        {
+            use sqlx_core::{connection::Connection as _, executor::Execute as _};
+
+            let queries = queries.into_iter().collect::<Vec<_>>();
+            let statements = queries.iter().map(|query| query.sql()).collect::<Vec<_>>();
+            crate::span::instrument_batch(&statements, async move {
+                let results = async move {
+                    let mut sqlite = self.inner.lock().await;
+                    let mut tx = sqlite.begin().await?;
+                    let mut results = Vec::new();
+                    for mut query in queries {
+                        let arguments = crate::statement::take_checked_arguments(&mut query)?;
+                        let (mut rows, mut query_result) = (Vec::new(), D1QueryResult::default());
+                        for step in
+                            crate::native::execute_on(&mut tx, query.sql(), arguments).await?
+                        {
+                            match step {
+                                sqlx_core::Either::Left(r) => query_result.extend([r]),
+                                sqlx_core::Either::Right(row) => rows.push(row),
+                            }
+                        }
+                        results.push(D1BatchResult { rows, query_result });
+                    }
+                    tx.commit().await?;
+                    Ok(results)
+                }
+                .await;
+                record_batch(&results);
+                results
+            })
+            .await
+        }
+        #[cfg(target_arch = "wasm32")]
+        {
+            batch_on(&self.inner, &self.settings.retry_policy, queries).await
+        }
+    }
+}
+
+#[cfg(target_arch = "wasm32")]
+pub(crate) async fn batch_on<'q>(
+    d1: &impl D1Binding,
+    retry_policy: &crate::D1RetryPolicy,
+    queries: impl IntoIterator<Item = Query<'q, D1, D1Arguments>>,
+) -> Result<Vec<D1BatchResult>, sqlx_core::Error> {
+    use sqlx_core::executor::Execute as _;
+
+    let queries = queries.into_iter().collect::<Vec<_>>();
+    let sqls = queries.iter().map(|query| query.sql()).collect::<Vec<_>>();
+    crate::span::instrument_batch(&sqls, async move {
+        let results = async move {
+            let statements = js_sys::Array::new();
+            let mut idempotent = true;
+            for mut query in queries {
+                idempotent &= crate::statement::StatementKind::of(query.sql()).is_idempotent();
+                let prepared = query.statement().and_then(|s| s.prepared.clone());
+                let arguments = crate::statement::take_checked_arguments(&mut query)?;
+                let statement = d1
+                    .prepare_or_reuse(query.sql(), prepared.as_ref(), arguments)
+                    .map_err(|e| sqlx_core::Error::from(D1Error::from(e)))?;
+                statements.push(&statement);
+            }
+
+            let statements = &statements;
+            let d1_results = retry_policy
+                .run(idempotent, || async move {
+                    JsFuture::from(d1.batch(statements.clone())?).await
+                })
+                .await
+                .map_err(D1Error::from)?;
+
+            d1_results
+                .unchecked_into::<js_sys::Array>()
+                .iter()
+                .map(|d1_result| D1BatchResult::from_raw(worker_sys::D1Result::from(d1_result)))
+                .collect()
+        }
+        .await;
+        record_batch(&results);
+        results
+    })
+    .await
+}
+
+/// Record the results to the current span created by `span::instrument_batch`,
+/// summing them up.
+fn record_batch(results: &Result<Vec<D1BatchResult>, sqlx_core::Error>) {
+    match results {
+        Ok(results) => {
+            let mut query_result = D1QueryResult::default();
+            for r in results {
+                query_result.rows_affected += r.query_result.rows_affected;
+                query_result.last_insert_row_id = r.query_result.last_insert_row_id;
+                query_result.meta.accumulate(r.query_result.meta.clone());
+            }
+            crate::span::record(results.iter().map(|r| r.rows.len()).sum(), &query_result);
+        }
+        Err(e) => crate::span::record_error(e),
+    }
+}
+
+#[cfg(target_arch = "wasm32")]
+impl D1BatchResult {
+    fn from_raw(d1_result: worker_sys::D1Result) -> Result<Self, sqlx_core::Error> {
+        let query_result = D1QueryResult::from_raw_meta(
+            &js_sys::Reflect::get(&d1_result, &"meta".into()).map_err(D1Error::from)?,
+        );
+        let rows = RawResultSet::from_raw_objects(
+            d1_result
+                .results()
+                .map_err(D1Error::from)?
+                .unwrap_or_else(js_sys::Array::new),
+        )
+        .collect::<Result<_, _>>()?;
+        Ok(Self { rows, query_result })
+    }
+}
@@ -34,17 +34,16 @@ impl sqlx_core::column::Column for D1Column {
 
 impl sqlx_core::column::ColumnIndex<crate::row::D1Row> for &'_ str {
     fn index(&self, row: &crate::row::D1Row) -> Result<usize, sqlx_core::Error> {
-        use sqlx_core::row::Row as _;
-        row.columns()
-            .iter()
-            .position(|c| &*c.name == *self)
+        row.column_names
+            .get(*self)
+            .copied()
             .ok_or_else(|| sqlx_core::Error::ColumnNotFound(self.to_string()))
     }
 }
 impl sqlx_core::column::ColumnIndex<crate::row::D1Row> for usize {
     fn index(&self, row: &crate::row::D1Row) -> Result<usize, sqlx_core::Error> {
         use sqlx_core::row::Row as _;
-        (*self <= row.columns().len())
+        (*self < row.columns().len())
             .then_some(*self)
             .ok_or_else(|| sqlx_core::Error::ColumnIndexOutOfBounds {
                 index: *self,
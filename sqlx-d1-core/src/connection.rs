@@ -2,27 +2,22 @@ use sqlx_core::{Either, Url};
 
 #[cfg(target_arch = "wasm32")]
 use {
-    crate::{error::D1Error, row::D1Row},
+    crate::{
+        error::D1Error,
+        ffi::{self, D1Binding},
+        logger::QueryLogger,
+        observer::Observation,
+        query_result::D1QueryResult,
+        row::{D1Row, RawResultSet},
+    },
     std::pin::Pin,
-    worker::{js_sys, wasm_bindgen::JsValue, wasm_bindgen_futures::JsFuture},
+    worker::{
+        js_sys,
+        wasm_bindgen::{JsCast, JsValue},
+        wasm_bindgen_futures::JsFuture,
+    },
 };
 
-#[cfg(not(target_arch = "wasm32"))]
-macro_rules! unreachable_native_impl_of_item_for_only_wasm32 {
-    ($item_for_only_wasm32:literal) => {
-        panic!(
-            "native `{}`: Invalid use of `sqlx_d1`. Be sure to use `sqlx_d1` where the target is set to \
-            `wasm32-unknown-unknown` ! \n\
-            For this, typcally, place `.cargo/config.toml` of following content at the root of \
-            your project or workspace : \n\
-            \n\
-            [build]\n\
-            target = \"wasm32-unknown-unknown\"\n",
-            $item_for_only_wasm32
-        )
-    };
-}
-
 /// ## Example
 ///
 /// ```toml
@@ -86,8 +81,16 @@ pub struct D1Connection {
     #[cfg(target_arch = "wasm32")]
     pub(crate) inner: worker_sys::D1Database,
 
+    /// shared by clones like the D1 binding is
     #[cfg(not(target_arch = "wasm32"))]
-    pub(crate) inner: sqlx_sqlite::SqliteConnection,
+    pub(crate) inner: std::sync::Arc<futures_util::lock::Mutex<sqlx_sqlite::SqliteConnection>>,
+
+    pub(crate) transaction: Option<crate::transaction::DeferredStatements>,
+
+    /// own to each clone, set by `Connection::close`
+    pub(crate) closed: bool,
+
+    pub(crate) settings: QuerySettings,
 }
 
 const _: () = {
@@ -104,6 +107,9 @@ const _: () = {
                     inner: unsafe {
                         std::mem::transmute::<worker::D1Database, worker_sys::D1Database>(d1)
                     },
+                    transaction: None,
+                    closed: false,
+                    settings: QuerySettings::default(),
                 }
             }
             #[cfg(not(target_arch = "wasm32"))]
@@ -113,23 +119,60 @@ const _: () = {
             }
         }
 
+        /// Register an observer called before and after each query.
+        pub fn observer(mut self, observer: impl crate::D1QueryObserver) -> Self {
+            self.settings.observers.push(observer);
+            self
+        }
+
         #[cfg(not(target_arch = "wasm32"))]
         pub async fn connect(url: impl AsRef<str>) -> Result<Self, sqlx_core::Error> {
             <Self as sqlx_core::connection::Connection>::connect(url.as_ref()).await
         }
+
+        /// Error if the statement can't be executed right now, bypassing the
+        /// transaction where writes are deferred until commit.
+        pub(crate) fn ensure_immediate(&self, sql: &str) -> Result<(), sqlx_core::Error> {
+            match &self.transaction {
+                Some(deferred) => deferred.check_immediate(sql),
+                None => Ok(()),
+            }
+        }
+
+        /// Error if this connection is in a transaction, for operations that
+        /// can't be deferred until commit.
+        pub(crate) fn ensure_no_transaction(
+            &self,
+            operation: &str,
+        ) -> Result<(), sqlx_core::Error> {
+            match &self.transaction {
+                Some(_) => Err(sqlx_core::Error::InvalidArgument(format!(
+                    "D1 transaction can't defer `{operation}` until commit"
+                ))),
+                None => Ok(()),
+            }
+        }
+
+        /// Error if this connection is closed.
+        pub(crate) fn ensure_open(&self) -> Result<(), sqlx_core::Error> {
+            if self.closed {
+                Err(sqlx_core::Error::Io(std::io::Error::new(
+                    std::io::ErrorKind::NotConnected,
+                    "`D1Connection` is closed",
+                )))
+            } else {
+                Ok(())
+            }
+        }
     }
 
     impl Clone for D1Connection {
         fn clone(&self) -> Self {
-            #[cfg(target_arch = "wasm32")]
-            {
-                Self {
-                    inner: self.inner.clone(),
-                }
-            }
-            #[cfg(not(target_arch = "wasm32"))]
-            {
-                unreachable_native_impl_of_item_for_only_wasm32!("impl Clone for D1Connection");
+            Self {
+                inner: self.inner.clone(),
+                transaction: None,
+                closed: false,
+                settings: self.settings.clone(),
             }
         }
     }
@@ -145,16 +188,63 @@ const _: () = {
 
         type Options = D1ConnectOptions;
 
-        fn close(self) -> crate::ResultFuture<'static, ()> {
-            Box::pin(async { Ok(()) })
+        /// Close this connection, making its later use an error.
+        ///
+        /// D1 has no connection to close, so this doesn't affect its clones
+        /// (like the ones acquired from a `D1Pool`) nor other `D1Connection`s
+        /// created from the same binding.
+        fn close(mut self) -> crate::ResultFuture<'static, ()> {
+            self.closed = true;
+
+            #[cfg(target_arch = "wasm32")]
+            {
+                Box::pin(async { Ok(()) })
+            }
+            #[cfg(not(target_arch = "wasm32"))]
+            {
+                match std::sync::Arc::try_unwrap(self.inner) {
+                    Ok(sqlite) => sqlite.into_inner().close(),
+                    Err(_ /* still shared by clones */) => Box::pin(async { Ok(()) }),
+                }
+            }
         }
 
-        fn close_hard(self) -> crate::ResultFuture<'static, ()> {
-            Box::pin(async { Ok(()) })
+        fn close_hard(mut self) -> crate::ResultFuture<'static, ()> {
+            self.closed = true;
+
+            #[cfg(target_arch = "wasm32")]
+            {
+                Box::pin(async { Ok(()) })
+            }
+            #[cfg(not(target_arch = "wasm32"))]
+            {
+                match std::sync::Arc::try_unwrap(self.inner) {
+                    Ok(sqlite) => sqlite.into_inner().close_hard(),
+                    Err(_ /* still shared by clones */) => Box::pin(async { Ok(()) }),
+                }
+            }
         }
 
+        /// Run `SELECT 1` through D1 to check the binding and the database are available.
         fn ping(&mut self) -> crate::ResultFuture<'_, ()> {
-            Box::pin(async { Ok(()) })
+            if let Err(e) = self.ensure_open() {
+                return Box::pin(async { Err(e) });
+            }
+
+            #[cfg(target_arch = "wasm32")]
+            {
+                Box::pin(async move {
+                    <&D1Connection as sqlx_core::executor::Executor>::fetch_optional(
+                        &*self, "SELECT 1",
+                    )
+                    .await
+                    .map(|_| ())
+                })
+            }
+            #[cfg(not(target_arch = "wasm32"))]
+            {
+                Box::pin(async move { self.inner.lock().await.ping().await })
+            }
         }
 
         fn begin(
@@ -179,12 +269,14 @@ const _: () = {
         }
     }
 
+    /// In a transaction, write statements are deferred until commit and `execute`
+    /// of them returns a placeholder `D1QueryResult` with `rows_affected` of `0`.
     impl<'c> sqlx_core::executor::Executor<'c> for &'c mut D1Connection {
         type Database = crate::D1;
 
         fn fetch_many<'e, 'q: 'e, E>(
             self,
-            #[allow(unused)] mut query: E,
+            mut query: E,
         ) -> futures_core::stream::BoxStream<
             'e,
             Result<
@@ -199,78 +291,55 @@ const _: () = {
             'c: 'e,
             E: 'q + sqlx_core::executor::Execute<'q, Self::Database>,
         {
+            if let Some(deferred) = &mut self.transaction
+                && let Some(result) = deferred.defer(&mut query)
+            {
+                return Box::pin(futures_util::stream::once(async {
+                    result.map(|()| Either::Left(Default::default()))
+                }));
+            }
+
             <&'c D1Connection as sqlx_core::executor::Executor<'c>>::fetch_many(self, query)
         }
 
         fn fetch_optional<'e, 'q: 'e, E>(
             self,
-            #[allow(unused)] mut query: E,
+            mut query: E,
         ) -> crate::ResultFuture<'e, Option<<Self::Database as sqlx_core::database::Database>::Row>>
         where
             'c: 'e,
             E: 'q + sqlx_core::executor::Execute<'q, Self::Database>,
         {
+            if let Some(deferred) = &mut self.transaction
+                && let Some(result) = deferred.defer(&mut query)
+            {
+                return Box::pin(async { result.map(|()| None) });
+            }
+
             <&'c D1Connection as sqlx_core::executor::Executor<'c>>::fetch_optional(self, query)
         }
 
         fn prepare_with<'e, 'q: 'e>(
             self,
             sql: &'q str,
-            _parameters: &'e [<Self::Database as sqlx_core::database::Database>::TypeInfo],
+            parameters: &'e [<Self::Database as sqlx_core::database::Database>::TypeInfo],
         ) -> crate::ResultFuture<'e, <Self::Database as sqlx_core::database::Database>::Statement<'q>>
         where
             'c: 'e,
         {
-            Box::pin(async {
-                Ok(crate::statement::D1Statement {
-                    sql: std::borrow::Cow::Borrowed(sql),
-                })
-            })
+            <&'c D1Connection as sqlx_core::executor::Executor<'c>>::prepare_with(
+                self, sql, parameters,
+            )
         }
 
         fn describe<'e, 'q: 'e>(
             self,
-            #[allow(unused)] sql: &'q str,
+            sql: &'q str,
         ) -> crate::ResultFuture<'e, sqlx_core::describe::Describe<Self::Database>>
         where
             'c: 'e,
         {
-            #[cfg(target_arch = "wasm32")]
-            {
-                unreachable!("wasm32 describe")
-            }
-            #[cfg(not(target_arch = "wasm32"))]
-            {
-                /* compile-time verification by macros */
-
-                Box::pin(async {
-                    let sqlx_core::describe::Describe {
-                        columns,
-                        parameters,
-                        nullable
-                    } = <&mut sqlx_sqlite::SqliteConnection as sqlx_core::executor::Executor>::describe(
-                        &mut self.inner,
-                        sql
-                    ).await?;
-
-                    Ok(sqlx_core::describe::Describe {
-                        parameters: parameters.map(|ps| match ps {
-                            Either::Left(type_infos) => Either::Left(
-                                type_infos
-                                    .into_iter()
-                                    .map(crate::type_info::D1TypeInfo::from_sqlite)
-                                    .collect(),
-                            ),
-                            Either::Right(n) => Either::Right(n),
-                        }),
-                        columns: columns
-                            .into_iter()
-                            .map(crate::column::D1Column::from_sqlite)
-                            .collect(),
-                        nullable,
-                    })
-                })
-            }
+            <&'c D1Connection as sqlx_core::executor::Executor<'c>>::describe(self, sql)
         }
     }
 
@@ -294,103 +363,55 @@ const _: () = {
             'c: 'e,
             E: 'q + sqlx_core::executor::Execute<'q, Self::Database>,
         {
+            if let Err(e) = self.ensure_immediate(query.sql()) {
+                return Box::pin(futures_util::stream::once(async { Err(e) }));
+            }
+
             #[cfg(not(target_arch = "wasm32"))]
             {
-                unreachable_native_impl_of_item_for_only_wasm32!("impl Executor for &D1Conection");
+                use futures_util::TryStreamExt as _;
+
+                Box::pin(
+                    futures_util::stream::once(crate::native::fetch_all(self, query))
+                        .map_ok(|steps| futures_util::stream::iter(steps.into_iter().map(Ok)))
+                        .try_flatten(),
+                )
             }
             #[cfg(target_arch = "wasm32")]
             {
-                let sql = query.sql();
-                let arguments = match query.take_arguments() {
-                    Ok(a) => a,
-                    Err(e) => {
-                        return Box::pin(futures_util::stream::once(async {
-                            Err(sqlx_core::Error::Encode(e))
-                        }));
-                    }
-                };
-
-                struct FetchMany<F> {
-                    raw_rows_future: F,
-                    raw_rows: Option<js_sys::ArrayIntoIter>,
+                if let Err(e) = self.ensure_open() {
+                    return Box::pin(futures_util::stream::once(async { Err(e) }));
                 }
-                const _: () = {
-                    /* SAFETY: used in single-threaded Workers */
-                    unsafe impl<F> Send for FetchMany<F> {}
-
-                    impl<F> FetchMany<F> {
-                        fn new(raw_rows_future: F) -> Self {
-                            Self {
-                                raw_rows_future,
-                                raw_rows: None,
-                            }
-                        }
-                    }
-
-                    impl<F> futures_core::Stream for FetchMany<F>
-                    where
-                        F: Future<Output = Result<Option<js_sys::Array>, JsValue>>,
-                    {
-                        type Item = Result<
-                            Either<crate::query_result::D1QueryResult, D1Row>,
-                            sqlx_core::Error,
-                        >;
-
-                        fn poll_next(
-                            self: Pin<&mut Self>,
-                            cx: &mut std::task::Context<'_>,
-                        ) -> std::task::Poll<Option<Self::Item>> {
-                            use std::task::Poll;
-
-                            fn pop_next(
-                                raw_rows: &mut js_sys::ArrayIntoIter,
-                            ) -> Option<
-                                Result<
-                                    Either<crate::query_result::D1QueryResult, D1Row>,
-                                    sqlx_core::Error,
-                                >,
-                            > {
-                                let raw_row = raw_rows.next()?;
-                                Some(D1Row::from_raw(raw_row).map(Either::Right))
-                            }
 
-                            let this = unsafe { self.get_unchecked_mut() };
-                            match &mut this.raw_rows {
-                                Some(raw_rows) => Poll::Ready(pop_next(raw_rows)),
-                                None => {
-                                    match unsafe { Pin::new_unchecked(&mut this.raw_rows_future) }
-                                        .poll(cx)
-                                    {
-                                        Poll::Pending => Poll::Pending,
-                                        Poll::Ready(Err(e)) => Poll::Ready(Some(Err(
-                                            sqlx_core::Error::from(D1Error::from(e)),
-                                        ))),
-                                        Poll::Ready(Ok(maybe_raw_rows)) => {
-                                            this.raw_rows = Some(
-                                                maybe_raw_rows
-                                                    .unwrap_or_else(js_sys::Array::new)
-                                                    .into_iter(),
-                                            );
-                                            Poll::Ready(pop_next(unsafe {
-                                                this.raw_rows.as_mut().unwrap_unchecked()
-                                            }))
-                                        }
-                                    }
-                                }
+                /* D1 can't prepare multiple statements, so route them to `exec` */
+                let sql = query.sql();
+                if crate::statement::has_multiple_statements(sql) {
+                    return Box::pin(futures_util::stream::once(worker::send::SendFuture::new(
+                        async move {
+                            if query
+                                .take_arguments()
+                                .map_err(sqlx_core::Error::Encode)?
+                                .is_some_and(|a| !a.as_ref().is_empty())
+                            {
+                                return Err(sqlx_core::Error::InvalidArgument(
+                                    "D1 doesn't support parameters in multiple statements".into(),
+                                ));
                             }
-                        }
-                    }
-                };
 
-                Box::pin(FetchMany::new(async move {
-                    let mut statement = self.inner.prepare(sql).unwrap();
-                    if let Some(a) = arguments {
-                        statement = statement.bind(a.as_ref().iter().collect())?;
-                    }
+                            let crate::D1ExecResult { duration, .. } =
+                                self.exec_script(sql).await?;
+                            Ok(Either::Left(D1QueryResult {
+                                meta: crate::D1QueryMeta {
+                                    duration,
+                                    ..Default::default()
+                                },
+                                ..Default::default()
+                            }))
+                        },
+                    )));
+                }
 
-                    let d1_result_jsvalue = JsFuture::from(statement.all()?).await?;
-                    worker_sys::D1Result::from(d1_result_jsvalue).results()
-                }))
+                fetch_many_on(&self.inner, &self.settings, query)
             }
         }
 
@@ -402,35 +423,24 @@ const _: () = {
             'c: 'e,
             E: 'q + sqlx_core::executor::Execute<'q, Self::Database>,
         {
+            if let Err(e) = self.ensure_immediate(query.sql()) {
+                return Box::pin(async { Err(e) });
+            }
+
             #[cfg(not(target_arch = "wasm32"))]
             {
-                unreachable_native_impl_of_item_for_only_wasm32!("impl Executor for &D1Conection");
+                Box::pin(async move {
+                    let steps = crate::native::fetch_all(self, query).await?;
+                    Ok(steps.into_iter().find_map(Either::right))
+                })
             }
             #[cfg(target_arch = "wasm32")]
             {
-                let sql = query.sql();
-                let arguments = match query.take_arguments() {
-                    Ok(a) => a,
-                    Err(e) => return Box::pin(async { Err(sqlx_core::Error::Encode(e)) }),
-                };
-
-                Box::pin(worker::send::SendFuture::new(async move {
-                    let mut statement = self.inner.prepare(sql).unwrap();
-                    if let Some(a) = arguments {
-                        statement = statement
-                            .bind(a.as_ref().iter().collect())
-                            .map_err(|e| sqlx_core::Error::Encode(Box::new(D1Error::from(e))))?;
-                    }
+                if let Err(e) = self.ensure_open() {
+                    return Box::pin(async { Err(e) });
+                }
 
-                    let raw = JsFuture::from(statement.first(None).map_err(D1Error::from)?)
-                        .await
-                        .map_err(D1Error::from)?;
-                    if raw.is_null() {
-                        Ok(None)
-                    } else {
-                        D1Row::from_raw(raw).map(Some)
-                    }
-                }))
+                fetch_optional_on(&self.inner, &self.settings, query)
             }
         }
 
@@ -442,11 +452,24 @@ const _: () = {
         where
             'c: 'e,
         {
-            Box::pin(async {
-                Ok(crate::statement::D1Statement {
-                    sql: std::borrow::Cow::Borrowed(sql),
+            #[cfg(not(target_arch = "wasm32"))]
+            {
+                Box::pin(async move {
+                    let describe =
+                        <&'c D1Connection as sqlx_core::executor::Executor<'c>>::describe(
+                            self, sql,
+                        )
+                        .await?;
+                    Ok(crate::statement::D1Statement::from_describe(sql, describe))
                 })
-            })
+            }
+            #[cfg(target_arch = "wasm32")]
+            {
+                let statement = self
+                    .ensure_open()
+                    .and_then(|()| crate::statement::D1Statement::prepare_on(&self.inner, sql));
+                Box::pin(async { statement })
+            }
         }
 
         fn describe<'e, 'q: 'e>(
@@ -456,22 +479,291 @@ const _: () = {
         where
             'c: 'e,
         {
-            #[cfg(not(target_arch = "wasm32"))]
-            {
-                unreachable_native_impl_of_item_for_only_wasm32!("impl Executor for &D1Conection");
-            }
             #[cfg(target_arch = "wasm32")]
             {
                 unreachable!("wasm32 describe")
             }
+            #[cfg(not(target_arch = "wasm32"))]
+            {
+                /* compile-time verification by macros */
+
+                Box::pin(async move {
+                    self.ensure_open()?;
+
+                    let sqlx_core::describe::Describe {
+                        columns,
+                        parameters,
+                        nullable
+                    } = <&mut sqlx_sqlite::SqliteConnection as sqlx_core::executor::Executor>::describe(
+                        &mut *self.inner.lock().await,
+                        sql
+                    ).await?;
+
+                    Ok(sqlx_core::describe::Describe {
+                        parameters: parameters.map(|ps| match ps {
+                            Either::Left(type_infos) => Either::Left(
+                                type_infos
+                                    .into_iter()
+                                    .map(crate::type_info::D1TypeInfo::from_sqlite)
+                                    .collect(),
+                            ),
+                            Either::Right(n) => Either::Right(n),
+                        }),
+                        columns: columns
+                            .into_iter()
+                            .map(crate::column::D1Column::from_sqlite)
+                            .collect(),
+                        nullable,
+                    })
+                })
+            }
         }
     }
 };
 
+#[cfg(target_arch = "wasm32")]
+pub(crate) fn fetch_many_on<'e, 'q: 'e, E>(
+    d1: &'e impl D1Binding,
+    settings: &'e QuerySettings,
+    mut query: E,
+) -> futures_core::stream::BoxStream<'e, Result<Either<D1QueryResult, D1Row>, sqlx_core::Error>>
+where
+    E: 'q + sqlx_core::executor::Execute<'q, crate::D1>,
+{
+    let sql = query.sql();
+    let with_meta = FETCH_WITH_META.get();
+    let prepared = query.statement().and_then(|s| s.prepared.clone());
+    let arguments = match crate::statement::take_checked_arguments(&mut query) {
+        Ok(a) => a,
+        Err(e) => return Box::pin(futures_util::stream::once(async { Err(e) })),
+    };
+
+    struct FetchMany<F> {
+        d1_result_future: F,
+        d1_result: Option<(RawResultSet, Option<D1QueryResult>)>,
+    }
+    const _: () = {
+        /* SAFETY: used in single-threaded Workers */
+        unsafe impl<F> Send for FetchMany<F> {}
+
+        impl<F> FetchMany<F> {
+            fn new(d1_result_future: F) -> Self {
+                Self {
+                    d1_result_future,
+                    d1_result: None,
+                }
+            }
+        }
+
+        impl<F> futures_core::Stream for FetchMany<F>
+        where
+            F: Future<Output = Result<(RawResultSet, D1QueryResult), JsValue>>,
+        {
+            type Item = Result<Either<D1QueryResult, D1Row>, sqlx_core::Error>;
+
+            fn poll_next(
+                self: Pin<&mut Self>,
+                cx: &mut std::task::Context<'_>,
+            ) -> std::task::Poll<Option<Self::Item>> {
+                use std::task::Poll;
+
+                /* yields all rows, and then the query result as the last item */
+                fn pop_next(
+                    (rows, query_result): &mut (RawResultSet, Option<D1QueryResult>),
+                ) -> Option<Result<Either<D1QueryResult, D1Row>, sqlx_core::Error>>
+                {
+                    match rows.next() {
+                        Some(row) => Some(row.map(Either::Right)),
+                        None => query_result.take().map(Either::Left).map(Ok),
+                    }
+                }
+
+                let this = unsafe { self.get_unchecked_mut() };
+                match &mut this.d1_result {
+                    Some(d1_result) => Poll::Ready(pop_next(d1_result)),
+                    None => {
+                        match unsafe { Pin::new_unchecked(&mut this.d1_result_future) }.poll(cx) {
+                            Poll::Pending => Poll::Pending,
+                            Poll::Ready(Err(e)) => {
+                                Poll::Ready(Some(Err(sqlx_core::Error::from(D1Error::from(e)))))
+                            }
+                            Poll::Ready(Ok((rows, query_result))) => {
+                                let d1_result = this.d1_result.insert((rows, Some(query_result)));
+                                Poll::Ready(pop_next(d1_result))
+                            }
+                        }
+                    }
+                }
+            }
+        }
+    };
+
+    Box::pin(FetchMany::new(crate::span::instrument(sql, async move {
+        let logger = QueryLogger::new(sql, arguments.as_ref(), &settings.log_settings);
+        let observation = Observation::start(&settings.observers, sql, arguments.as_ref());
+        let kind = crate::statement::StatementKind::of(sql);
+
+        let (rows, query_result) = async {
+            let statement = &d1.prepare_or_reuse(sql, prepared.as_ref(), arguments)?;
+            settings
+                .retry_policy
+                .run(kind.is_idempotent(), || async move {
+                    if kind.returns_rows() {
+                        fetch_rows(statement, kind, with_meta).await
+                    } else {
+                        /* `run` for statements returning no rows, to get just the metadata */
+                        let d1_result =
+                            worker_sys::D1Result::from(JsFuture::from(statement.run()?).await?);
+                        let query_result = D1QueryResult::from_raw_meta(&js_sys::Reflect::get(
+                            &d1_result,
+                            &JsValue::from_str("meta"),
+                        )?);
+                        Ok((RawResultSet::empty(), query_result))
+                    }
+                })
+                .await
+        }
+        .await
+        .inspect_err(|e| {
+            let e = sqlx_core::Error::from(D1Error::from(e.clone()));
+            logger.finish_with_error(&e);
+            crate::span::record_error(&e);
+            observation.finish(0, Err(&e));
+        })?;
+
+        logger.finish(rows.len(), query_result.rows_affected);
+        crate::span::record(rows.len(), &query_result);
+        observation.finish(rows.len(), Ok(&query_result));
+        Ok((rows, query_result))
+    })))
+}
+
+#[cfg(target_arch = "wasm32")]
+pub(crate) fn fetch_optional_on<'e, 'q: 'e, E>(
+    d1: &'e impl D1Binding,
+    settings: &'e QuerySettings,
+    mut query: E,
+) -> crate::ResultFuture<'e, Option<D1Row>>
+where
+    E: 'q + sqlx_core::executor::Execute<'q, crate::D1>,
+{
+    let sql = query.sql();
+    let prepared = query.statement().and_then(|s| s.prepared.clone());
+    let arguments = match crate::statement::take_checked_arguments(&mut query) {
+        Ok(a) => a,
+        Err(e) => return Box::pin(async { Err(e) }),
+    };
+
+    let fetch_optional = async move {
+        let logger = QueryLogger::new(sql, arguments.as_ref(), &settings.log_settings);
+        let observation = Observation::start(&settings.observers, sql, arguments.as_ref());
+        let kind = crate::statement::StatementKind::of(sql);
+
+        let (mut rows, query_result) = async {
+            let statement = &d1.prepare_or_reuse(sql, prepared.as_ref(), arguments)?;
+            settings
+                .retry_policy
+                .run(kind.is_idempotent(), || async move {
+                    fetch_rows(statement, kind, false).await
+                })
+                .await
+        }
+        .await
+        .map_err(|e| sqlx_core::Error::from(D1Error::from(e)))
+        .inspect_err(|e| {
+            logger.finish_with_error(e);
+            crate::span::record_error(e);
+            observation.finish(0, Err(e));
+        })?;
+
+        logger.finish(rows.len(), query_result.rows_affected);
+        crate::span::record(rows.len(), &query_result);
+        observation.finish(rows.len(), Ok(&query_result));
+        rows.next().transpose()
+    };
+
+    Box::pin(worker::send::SendFuture::new(crate::span::instrument(
+        sql,
+        fetch_optional,
+    )))
+}
+
+#[cfg(target_arch = "wasm32")]
+thread_local! {
+    /// set while `FetchWithMeta` makes or polls its query stream
+    static FETCH_WITH_META: std::cell::Cell<bool> = const { std::cell::Cell::new(false) };
+}
+
+/// Run `f` making the query streams fetch the rows together with D1's metadata.
+pub(crate) fn with_meta<T>(f: impl FnOnce() -> T) -> T {
+    #[cfg(target_arch = "wasm32")]
+    {
+        FETCH_WITH_META.set(true);
+        let output = f();
+        FETCH_WITH_META.set(false);
+        output
+    }
+    #[cfg(not(target_arch = "wasm32"))]
+    {
+        f()
+    }
+}
+
+/// Fetches the rows of the statement by a single D1 call.
+///
+/// `raw` keeps the order and duplicates of columns but D1 reports no metadata for it,
+/// so only `rows_affected` of `RETURNING` is known. `all` reports the metadata but its
+/// row objects can't keep duplicate column names, so it's used only `with_meta`.
+#[cfg(target_arch = "wasm32")]
+async fn fetch_rows(
+    statement: &worker_sys::D1PreparedStatement,
+    kind: crate::statement::StatementKind,
+    with_meta: bool,
+) -> Result<(RawResultSet, D1QueryResult), JsValue> {
+    if with_meta {
+        let d1_result = worker_sys::D1Result::from(JsFuture::from(statement.all()?).await?);
+        Ok((
+            RawResultSet::from_raw_objects(d1_result.results()?.unwrap_or_else(js_sys::Array::new)),
+            D1QueryResult::from_raw_meta(&js_sys::Reflect::get(
+                &d1_result,
+                &JsValue::from_str("meta"),
+            )?),
+        ))
+    } else {
+        let rows = RawResultSet::new(
+            JsFuture::from(ffi::raw_with_column_names(statement)?)
+                .await?
+                .unchecked_into(),
+        );
+        /* `RETURNING` returns a row for each changed row */
+        let rows_affected = match kind {
+            crate::statement::StatementKind::MutationReturning => rows.len(),
+            _ => 0,
+        };
+        Ok((
+            rows,
+            D1QueryResult {
+                rows_affected,
+                ..Default::default()
+            },
+        ))
+    }
+}
+
+/// Settings applied to every query executed on a D1 binding.
+#[derive(Debug, Clone, Default)]
+#[cfg_attr(not(target_arch = "wasm32"), allow(dead_code))]
+pub(crate) struct QuerySettings {
+    pub(crate) retry_policy: crate::D1RetryPolicy,
+    pub(crate) log_settings: crate::logger::D1LogSettings,
+    pub(crate) observers: crate::observer::Observers,
+}
+
 /// ref: <https://developers.cloudflare.com/d1/sql-api/sql-statements/#compatible-pragma-statements>
 #[derive(Clone)]
 pub struct D1ConnectOptions {
     pragmas: TogglePragmas,
+    settings: QuerySettings,
     #[cfg(target_arch = "wasm32")]
     d1: worker_sys::D1Database,
     #[cfg(not(target_arch = "wasm32"))]
@@ -482,16 +774,6 @@ const _: () = {
     unsafe impl Send for D1ConnectOptions {}
     unsafe impl Sync for D1ConnectOptions {}
 
-    #[cfg(target_arch = "wasm32")]
-    const URL_CONVERSION_UNSUPPORTED_MESSAGE: &str = "\
-        `sqlx_d1::D1ConnectOptions` doesn't support conversion between `Url`. \
-        Consider connect from options created by `D1ConnectOptions::new`. \
-    ";
-
-    const LOG_SETTINGS_UNSUPPORTED_MESSAGE: &str = "\
-        `sqlx_d1::D1ConnectOptions` doesn't support log settings.
-    ";
-
     impl D1ConnectOptions {
         pub fn new(#[allow(unused)] d1: worker::D1Database) -> Self {
             #[cfg(target_arch = "wasm32")]
@@ -502,6 +784,7 @@ const _: () = {
                         core::mem::transmute::<worker::D1Database, worker_sys::D1Database>(d1)
                     },
                     pragmas: TogglePragmas::new(),
+                    settings: QuerySettings::default(),
                 }
             }
             #[cfg(not(target_arch = "wasm32"))]
@@ -510,20 +793,72 @@ const _: () = {
             }
         }
 
+        /// Options for a SQLite database file at `path` standing in for D1,
+        /// created if missing. Only available on native targets, mainly for tests.
+        #[cfg(not(target_arch = "wasm32"))]
+        pub fn sqlite(path: impl Into<std::path::PathBuf>) -> Self {
+            Self {
+                pragmas: TogglePragmas::new(),
+                settings: QuerySettings::default(),
+                sqlite_path: path.into(),
+            }
+        }
+
+        /// Options for a fresh in-memory SQLite database standing in for D1.
+        /// Only available on native targets, mainly for tests.
+        ///
+        /// The database lives as long as the connection and its clones.
+        #[cfg(not(target_arch = "wasm32"))]
+        pub fn sqlite_in_memory() -> Self {
+            Self::sqlite(":memory:")
+        }
+
+        /// Set the policy to retry queries failed with transient errors.
+        ///
+        /// Retrying is not needed on native targets, and this is just ignored there.
+        pub fn retry_policy(mut self, retry_policy: crate::D1RetryPolicy) -> Self {
+            self.settings.retry_policy = retry_policy;
+            self
+        }
+
+        /// Register an observer called before and after each query.
+        pub fn observer(mut self, observer: impl crate::D1QueryObserver) -> Self {
+            self.settings.observers.push(observer);
+            self
+        }
+
+        /// Whether to log values of parameters with statements.
+        /// Default is `false`, where they are redacted.
+        pub fn log_parameter_values(mut self, yes: bool) -> Self {
+            self.settings.log_settings.parameter_values = yes;
+            self
+        }
+
         pub async fn connect(self) -> Result<D1Connection, crate::error::D1Error> {
             #[cfg(target_arch = "wasm32")]
             {
-                let Self { d1, pragmas } = self;
+                let Self {
+                    d1,
+                    pragmas,
+                    settings,
+                } = self;
                 if let Some(pragmas) = pragmas.collect() {
                     JsFuture::from(d1.exec(&pragmas.join("\n")).map_err(D1Error::from)?)
                         .await
                         .map_err(D1Error::from)?;
                 }
-                Ok(D1Connection { inner: d1 })
+                Ok(D1Connection {
+                    inner: d1,
+                    transaction: None,
+                    closed: false,
+                    settings,
+                })
             }
             #[cfg(not(target_arch = "wasm32"))]
             {
-                unreachable_native_impl_of_item_for_only_wasm32!("D1ConnectOptions::connect");
+                <Self as sqlx_core::connection::ConnectOptions>::connect(&self)
+                    .await
+                    .map_err(crate::error::D1Error::from_rust)
             }
         }
     }
@@ -532,6 +867,7 @@ const _: () = {
         fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
             f.debug_struct("D1ConnectOptions")
                 .field("pragmas", &self.pragmas)
+                .field("settings", &self.settings)
                 .finish()
         }
     }
@@ -539,12 +875,14 @@ const _: () = {
     impl std::str::FromStr for D1ConnectOptions {
         type Err = sqlx_core::Error;
 
-        fn from_str(_: &str) -> Result<Self, Self::Err> {
+        /// On wasm, accepts `d1://BINDING` after `D1ConnectOptions::register_env`.
+        fn from_str(#[allow(unused)] s: &str) -> Result<Self, Self::Err> {
             #[cfg(target_arch = "wasm32")]
             {
-                Err(sqlx_core::Error::Configuration(From::from(
-                    URL_CONVERSION_UNSUPPORTED_MESSAGE,
-                )))
+                let url = s
+                    .parse::<Url>()
+                    .map_err(|e| sqlx_core::Error::Configuration(Box::new(e)))?;
+                Self::from_registered_env(&url)
             }
 
             #[cfg(not(target_arch = "wasm32"))]
@@ -608,6 +946,7 @@ const _: () = {
 
                 Ok(Self {
                     pragmas: TogglePragmas::new(),
+                    settings: QuerySettings::default(),
                     sqlite_path,
                 })
             }
@@ -617,12 +956,11 @@ const _: () = {
     impl sqlx_core::connection::ConnectOptions for D1ConnectOptions {
         type Connection = D1Connection;
 
+        /// On wasm, accepts `d1://BINDING` after `D1ConnectOptions::register_env`.
         fn from_url(_url: &Url) -> Result<Self, sqlx_core::Error> {
             #[cfg(target_arch = "wasm32")]
             {
-                Err(sqlx_core::Error::Configuration(From::from(
-                    URL_CONVERSION_UNSUPPORTED_MESSAGE,
-                )))
+                Self::from_registered_env(_url)
             }
             #[cfg(not(target_arch = "wasm32"))]
             {
@@ -650,14 +988,23 @@ const _: () = {
             #[cfg(not(target_arch = "wasm32"))]
             {
                 Box::pin(async move {
-                    use sqlx_core::{connection::Connection, executor::Executor};
+                    use sqlx_core::executor::Executor;
 
-                    let mut sqlite_conn = sqlx_sqlite::SqliteConnection::connect(
-                        self.sqlite_path
-                            .to_str()
-                            .ok_or(sqlx_core::Error::WorkerCrashed)?,
-                    )
-                    .await?;
+                    let sqlite_options = if self.sqlite_path.as_os_str() == ":memory:" {
+                        sqlx_sqlite::SqliteConnectOptions::new().in_memory(true)
+                    } else {
+                        sqlx_sqlite::SqliteConnectOptions::new()
+                            .filename(&self.sqlite_path)
+                            .create_if_missing(true)
+                    };
+                    let log_settings = &self.settings.log_settings;
+                    let sqlite_options = sqlite_options
+                        .log_statements(log_settings.statements_level)
+                        .log_slow_statements(
+                            log_settings.slow_statements_level,
+                            log_settings.slow_statements_duration,
+                        );
+                    let mut sqlite_conn = sqlite_options.connect().await?;
 
                     if let Some(pragmas) = self.pragmas.collect() {
                         for pragma in pragmas {
@@ -665,17 +1012,32 @@ const _: () = {
                         }
                     }
 
-                    Ok(D1Connection { inner: sqlite_conn })
+                    Ok(D1Connection {
+                        inner: std::sync::Arc::new(futures_util::lock::Mutex::new(sqlite_conn)),
+                        transaction: None,
+                        closed: false,
+                        settings: self.settings.clone(),
+                    })
                 })
             }
         }
 
-        fn log_statements(self, _: log::LevelFilter) -> Self {
-            unreachable!("{LOG_SETTINGS_UNSUPPORTED_MESSAGE}")
+        /// Statements are logged through the `log` crate with target `sqlx::query`,
+        /// or to Workers console when no logger is set. Default is `Off`.
+        fn log_statements(mut self, level: log::LevelFilter) -> Self {
+            self.settings.log_settings.statements_level = level;
+            self
         }
 
-        fn log_slow_statements(self, _: log::LevelFilter, _: std::time::Duration) -> Self {
-            unreachable!("{LOG_SETTINGS_UNSUPPORTED_MESSAGE}")
+        /// Default is `Warn` for statements slower than 1s.
+        fn log_slow_statements(
+            mut self,
+            level: log::LevelFilter,
+            duration: std::time::Duration,
+        ) -> Self {
+            self.settings.log_settings.slow_statements_level = level;
+            self.settings.log_settings.slow_statements_duration = duration;
+            self
         }
     }
 };
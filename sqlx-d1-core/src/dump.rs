@@ -0,0 +1,234 @@
+//! ref: <https://developers.cloudflare.com/d1/worker-api/d1-database/#dump>
+
+use crate::D1Connection;
+
+#[cfg(target_arch = "wasm32")]
+use {
+    crate::error::D1Error,
+    worker::{js_sys, wasm_bindgen_futures::JsFuture},
+};
+
+/// Max number of statements sent in one batch on [`D1Connection::restore`].
+#[cfg(target_arch = "wasm32")]
+const MAX_STATEMENTS_PER_BATCH: usize = 100;
+/// Max total size of statements sent in one batch on [`D1Connection::restore`].
+#[cfg(target_arch = "wasm32")]
+const MAX_BYTES_PER_BATCH: usize = 1 << 20;
+
+impl D1Connection {
+    /// Dump the entire database as the raw SQLite database file.
+    ///
+    /// On native targets, this dumps the SQLite database `D1Connection` is connected to.
+    pub async fn dump(&self) -> Result<Vec<u8>, sqlx_core::Error> {
+        self.ensure_open()?;
+        #[cfg(target_arch = "wasm32")]
+        {
+            let array_buffer = JsFuture::from(self.inner.dump().map_err(D1Error::from)?)
+                .await
+                .map_err(D1Error::from)?;
+            Ok(js_sys::Uint8Array::new(&array_buffer).to_vec())
+        }
+        #[cfg(not(target_arch = "wasm32"))]
+        {
+            use sqlx_core::executor::Executor;
+
+            let snapshot_path = std::env::temp_dir().join(format!(
+                "sqlx-d1-dump-{}-{}.sqlite",
+                std::process::id(),
+                std::time::SystemTime::now()
+                    .duration_since(std::time::UNIX_EPOCH)
+                    .unwrap_or_default()
+                    .as_nanos()
+            ));
+
+            /* `VACUUM INTO` for a consistent snapshot including WAL contents. The target is
+            given as URI with `mode=rwc`, or it's opened with the flags of the database
+            and becomes another in-memory one for `sqlite_in_memory` */
+            let snapshot_uri = format!(
+                "file:{}?mode=rwc",
+                snapshot_path
+                    .display()
+                    .to_string()
+                    .replace('%', "%25")
+                    .replace('?', "%3f")
+                    .replace('#', "%23")
+            );
+            self.inner
+                .lock()
+                .await
+                .execute(&*format!(
+                    "VACUUM INTO '{}'",
+                    snapshot_uri.replace('\'', "''")
+                ))
+                .await?;
+
+            let dump = std::fs::read(&snapshot_path).map_err(|e| {
+                sqlx_core::Error::Configuration(
+                    format!("`VACUUM INTO` made no snapshot to dump: {e}").into(),
+                )
+            });
+            let _ = std::fs::remove_file(&snapshot_path);
+            dump
+        }
+    }
+
+    /// Restore the database from a SQL text dump, like the one of
+    /// `wrangler d1 export` or `sqlite3 .dump`.
+    ///
+    /// Statements are sent in chunks of batches, each of which is atomic but
+    /// the whole restoration is not. Transaction control statements in the dump
+    /// (`BEGIN TRANSACTION;`, `COMMIT;`, ...) are skipped as D1 doesn't support them.
+    ///
+    /// On native targets, this restores into the SQLite database `D1Connection` is
+    /// connected to, in a single transaction.
+    pub async fn restore(&self, sql_dump: &str) -> Result<(), sqlx_core::Error> {
+        self.ensure_open()?;
+        self.ensure_no_transaction("restore")?;
+        let statements = crate::statement::split_statements(sql_dump)
+            .into_iter()
+            .filter(|statement| {
+                let leading_keyword = statement
+                    .split(|c: char| !c.is_ascii_alphabetic())
+                    .next()
+                    .unwrap_or_default();
+                !["BEGIN", "COMMIT", "END", "ROLLBACK", "SAVEPOINT", "RELEASE"]
+                    .iter()
+                    .any(|k| leading_keyword.eq_ignore_ascii_case(k))
+            })
+            .collect::<Vec<_>>();
+
+        #[cfg(target_arch = "wasm32")]
+        {
+            let mut statements = statements.as_slice();
+            while !statements.is_empty() {
+                let (mut n, mut bytes) = (0, 0);
+                while n < statements.len()
+                    && n < MAX_STATEMENTS_PER_BATCH
+                    && (n == 0 || bytes + statements[n].len() <= MAX_BYTES_PER_BATCH)
+                {
+                    bytes += statements[n].len();
+                    n += 1;
+                }
+
+                let (chunk, rest) = statements.split_at(n);
+                crate::batch::batch_on(
+                    &self.inner,
+                    &self.settings.retry_policy,
+                    chunk.iter().map(|s| crate::query(s)),
+                )
+                .await?;
+                statements = rest;
+            }
+            Ok(())
+        }
+        #[cfg(not(target_arch = "wasm32"))]
+        {
+            use sqlx_core::{connection::Connection, executor::Executor};
+
+            let mut sqlite = self.inner.lock().await;
+            let mut tx = sqlite.begin().await?;
+            for statement in &statements {
+                (&mut *tx).execute(statement.as_str()).await?;
+            }
+            tx.commit().await
+        }
+    }
+}
+
+#[cfg(all(test, not(target_arch = "wasm32")))]
+mod tests {
+    use crate::{D1ConnectOptions, D1Connection};
+
+    async fn names_in(conn: &D1Connection) -> Vec<String> {
+        crate::query_scalar("SELECT name FROM users ORDER BY id")
+            .fetch_all(conn)
+            .await
+            .unwrap()
+    }
+
+    /// Open the dump as a SQLite database file.
+    async fn open_dump(dump: &[u8]) -> (D1Connection, std::path::PathBuf) {
+        let path = std::env::temp_dir().join(format!(
+            "sqlx-d1-dump-test-{}-{}.sqlite",
+            std::process::id(),
+            std::time::SystemTime::now()
+                .duration_since(std::time::UNIX_EPOCH)
+                .unwrap()
+                .as_nanos()
+        ));
+        std::fs::write(&path, dump).unwrap();
+        (
+            D1ConnectOptions::sqlite(&path).connect().await.unwrap(),
+            path,
+        )
+    }
+
+    #[test]
+    fn dump_in_memory_database() {
+        futures_lite::future::block_on(async {
+            let conn = D1ConnectOptions::sqlite_in_memory()
+                .connect()
+                .await
+                .unwrap();
+            conn.exec_script(
+                "CREATE TABLE users (id INTEGER PRIMARY KEY, name TEXT NOT NULL);\n\
+                INSERT INTO users (name) VALUES ('alice'), ('bob');",
+            )
+            .await
+            .unwrap();
+
+            let dump = conn.dump().await.unwrap();
+            assert!(dump.starts_with(b"SQLite format 3\0"));
+
+            let (dumped, path) = open_dump(&dump).await;
+            assert_eq!(names_in(&dumped).await, ["alice", "bob"]);
+            let _ = std::fs::remove_file(path);
+        });
+    }
+
+    #[test]
+    fn restore_and_dump_round_trip() {
+        futures_lite::future::block_on(async {
+            let conn = D1ConnectOptions::sqlite_in_memory()
+                .connect()
+                .await
+                .unwrap();
+            conn.restore(
+                "PRAGMA foreign_keys=OFF;\n\
+                BEGIN TRANSACTION;\n\
+                CREATE TABLE users (id INTEGER PRIMARY KEY, name TEXT NOT NULL);\n\
+                INSERT INTO users VALUES(1,'alice');\n\
+                INSERT INTO users VALUES(2,'bob; and -- not a comment');\n\
+                CREATE TABLE logs (user_id INTEGER, kind TEXT);\n\
+                CREATE TRIGGER log_users AFTER INSERT ON users BEGIN \
+                INSERT INTO logs VALUES (new.id, CASE WHEN new.name = 'carol' THEN 'c' ELSE 'other' END); \
+                END;\n\
+                COMMIT;",
+            )
+            .await
+            .unwrap();
+            assert_eq!(
+                names_in(&conn).await,
+                ["alice", "bob; and -- not a comment"]
+            );
+
+            crate::query("INSERT INTO users (name) VALUES ('carol')")
+                .execute(&conn)
+                .await
+                .unwrap();
+            let (dumped, path) = open_dump(&conn.dump().await.unwrap()).await;
+            assert_eq!(
+                names_in(&dumped).await,
+                ["alice", "bob; and -- not a comment", "carol"]
+            );
+            assert_eq!(
+                crate::query_scalar::<String>("SELECT kind FROM logs")
+                    .fetch_all(&dumped)
+                    .await
+                    .unwrap(),
+                ["c"]
+            );
+            let _ = std::fs::remove_file(path);
+        });
+    }
+}
@@ -0,0 +1,91 @@
+//! Creating connections from `worker::Env` by binding names.
+
+use crate::{D1ConnectOptions, D1Connection, error::D1Error};
+
+#[cfg(target_arch = "wasm32")]
+thread_local! {
+    /* Workers are single-threaded, so this is shared by all requests in the isolate */
+    static REGISTERED_ENV: std::cell::RefCell<Option<worker::Env>> = const { std::cell::RefCell::new(None) };
+}
+
+impl D1ConnectOptions {
+    /// Options for the D1 binding named `binding` in `env`.
+    pub fn from_env(
+        #[allow(unused)] env: &worker::Env,
+        #[allow(unused)] binding: &str,
+    ) -> Result<Self, sqlx_core::Error> {
+        #[cfg(not(target_arch = "wasm32"))]
+        {
+            unreachable_native_impl_of_item_for_only_wasm32!("D1ConnectOptions::from_env");
+        }
+        #[cfg(target_arch = "wasm32")]
+        {
+            Ok(Self::new(env.d1(binding).map_err(D1Error::from)?))
+        }
+    }
+
+    /// Register `env` so that `d1://BINDING` URLs are accepted by
+    /// `ConnectOptions::from_url` and `Connection::connect`.
+    ///
+    /// ## Example
+    ///
+    /// ```rust,ignore
+    /// use sqlx_d1::sqlx_core::connection::Connection;
+    ///
+    /// sqlx_d1::D1ConnectOptions::register_env(&env);
+    /// let conn = sqlx_d1::D1Connection::connect("d1://DB").await?;
+    /// ```
+    pub fn register_env(#[allow(unused)] env: &worker::Env) {
+        #[cfg(not(target_arch = "wasm32"))]
+        {
+            unreachable_native_impl_of_item_for_only_wasm32!("D1ConnectOptions::register_env");
+        }
+        #[cfg(target_arch = "wasm32")]
+        {
+            REGISTERED_ENV.with_borrow_mut(|registered| *registered = Some(env.clone()));
+        }
+    }
+
+    #[cfg(target_arch = "wasm32")]
+    pub(crate) fn from_registered_env(url: &sqlx_core::Url) -> Result<Self, sqlx_core::Error> {
+        let binding = Some(url)
+            .filter(|url| url.scheme() == "d1" && matches!(url.path(), "" | "/"))
+            .and_then(|url| url.host_str())
+            .filter(|binding| !binding.is_empty())
+            .ok_or_else(|| {
+                sqlx_core::Error::Configuration(
+                    format!("expected `d1://BINDING`, got `{url}`").into(),
+                )
+            })?;
+
+        REGISTERED_ENV.with_borrow(|registered| match registered {
+            Some(env) => Self::from_env(env, binding),
+            None => Err(sqlx_core::Error::Configuration(
+                "`worker::Env` is not registered. \
+                Call `D1ConnectOptions::register_env` before connecting by URL."
+                    .into(),
+            )),
+        })
+    }
+}
+
+/// Extension of `worker::Env` to get [`D1Connection`] by binding names.
+///
+/// ## Example
+///
+/// ```rust,ignore
+/// use sqlx_d1::EnvExt;
+///
+/// let users = env.sqlx_d1("USERS_DB")?;
+/// let logs = env.sqlx_d1("LOGS_DB")?;
+/// ```
+pub trait EnvExt {
+    /// Connection to the D1 binding named `binding`.
+    fn sqlx_d1(&self, binding: &str) -> Result<D1Connection, sqlx_core::Error>;
+}
+
+impl EnvExt for worker::Env {
+    fn sqlx_d1(&self, binding: &str) -> Result<D1Connection, sqlx_core::Error> {
+        Ok(D1Connection::new(self.d1(binding).map_err(D1Error::from)?))
+    }
+}
@@ -1,6 +1,22 @@
 pub struct D1Error(worker::send::SendWrapper<worker::Error>);
 
 impl D1Error {
+    /// Whether this is a transient error of D1 and the query may succeed by retrying.
+    ///
+    /// ref: <https://developers.cloudflare.com/d1/best-practices/retry-queries/>
+    pub fn is_transient(&self) -> bool {
+        const TRANSIENT_ERROR_MESSAGES: &[&str] = &[
+            "Network connection lost",
+            "storage caused object to be reset",
+            "reset because its code was updated",
+            "D1 DB is overloaded",
+            "Cannot resolve D1 DB due to transient issue",
+        ];
+
+        let message = self.to_string();
+        TRANSIENT_ERROR_MESSAGES.iter().any(|m| message.contains(m))
+    }
+
     pub(crate) fn from_rust(e: impl std::error::Error) -> Self {
         Self(worker::send::SendWrapper(worker::Error::RustError(
             e.to_string(),
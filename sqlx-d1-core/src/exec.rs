@@ -0,0 +1,86 @@
+//! ref: <https://developers.cloudflare.com/d1/worker-api/d1-database/#exec>
+
+use crate::D1Connection;
+
+#[cfg(target_arch = "wasm32")]
+use {
+    crate::error::D1Error,
+    worker::{js_sys, wasm_bindgen_futures::JsFuture},
+};
+
+/// Result of [`D1Connection::exec_script`].
+#[derive(Debug, Clone, PartialEq)]
+pub struct D1ExecResult {
+    /// number of executed statements
+    pub count: u32,
+    /// duration of the execution in milliseconds
+    pub duration: f64,
+}
+
+impl D1Connection {
+    /// Execute a raw SQL script containing one or more statements,
+    /// without binding parameters nor returning rows.
+    ///
+    /// Intended for maintenance tasks such as loading SQL files;
+    /// this is less performant and less safe than prepared statements.
+    ///
+    /// Note that D1 executes the script line by line, so comments are removed
+    /// and statements are put in each line. Line breaks in string literals are
+    /// not supported.
+    ///
+    /// ## Example
+    ///
+    /// ```rust,ignore
+    /// conn.exec_script(include_str!("../sql/reset_demo_data.sql")).await?;
+    /// ```
+    pub async fn exec_script(&self, sql: &str) -> Result<D1ExecResult, sqlx_core::Error> {
+        self.ensure_open()?;
+        self.ensure_no_transaction("exec_script")?;
+        crate::span::instrument_script(sql, async move {
+            let exec_result = async move {
+                #[cfg(not(target_arch = "wasm32"))]
+                {
+                    let started = std::time::Instant::now();
+                    let count = crate::statement::split_statements(sql).len() as u32;
+                    sqlx_core::raw_sql::raw_sql(sql)
+                        .execute(&mut *self.inner.lock().await)
+                        .await?;
+                    Ok(D1ExecResult {
+                        count,
+                        duration: started.elapsed().as_secs_f64() * 1000.,
+                    })
+                }
+                #[cfg(target_arch = "wasm32")]
+                {
+                    let script = crate::statement::split_statements(sql)
+                        .into_iter()
+                        .map(|statement| statement + ";")
+                        .collect::<Vec<_>>()
+                        .join("\n");
+
+                    let raw = JsFuture::from(self.inner.exec(&script).map_err(D1Error::from)?)
+                        .await
+                        .map_err(D1Error::from)?;
+
+                    let get = |key: &str| {
+                        js_sys::Reflect::get(&raw, &key.into())
+                            .ok()
+                            .and_then(|v| v.as_f64())
+                            .unwrap_or_default()
+                    };
+                    Ok(D1ExecResult {
+                        count: get("count") as u32,
+                        duration: get("duration"),
+                    })
+                }
+            }
+            .await;
+            match &exec_result {
+                Ok(exec_result) => crate::span::record_script(exec_result),
+                Err(e) => crate::span::record_error(e),
+            }
+            exec_result
+        })
+        .await
+    }
+}
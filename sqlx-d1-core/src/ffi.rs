@@ -0,0 +1,125 @@
+//! JS bindings not (yet) provided by `worker_sys`.
+
+#![cfg(target_arch = "wasm32")]
+
+use worker::js_sys;
+use worker::wasm_bindgen::{self, JsCast, JsValue, prelude::wasm_bindgen};
+use worker_sys::{D1Database, D1PreparedStatement};
+
+#[wasm_bindgen]
+extern "C" {
+    #[wasm_bindgen(extends = js_sys::Object)]
+    type D1DatabaseExt;
+
+    #[wasm_bindgen(method, catch, js_name = withSession)]
+    fn with_session(
+        this: &D1DatabaseExt,
+        constraint_or_bookmark: &str,
+    ) -> Result<D1DatabaseSession, JsValue>;
+
+    /// ref: <https://developers.cloudflare.com/d1/worker-api/d1-database/#withsession>
+    #[wasm_bindgen(extends = js_sys::Object)]
+    #[derive(Debug, Clone)]
+    pub(crate) type D1DatabaseSession;
+
+    #[wasm_bindgen(method, catch)]
+    fn prepare(this: &D1DatabaseSession, query: &str) -> Result<D1PreparedStatement, JsValue>;
+
+    #[wasm_bindgen(method, catch)]
+    fn batch(
+        this: &D1DatabaseSession,
+        statements: js_sys::Array,
+    ) -> Result<js_sys::Promise, JsValue>;
+
+    #[wasm_bindgen(method, js_name = getBookmark)]
+    pub(crate) fn get_bookmark(this: &D1DatabaseSession) -> Option<String>;
+
+    #[wasm_bindgen(extends = js_sys::Object)]
+    type D1PreparedStatementExt;
+
+    #[wasm_bindgen(method, catch, js_name = raw)]
+    fn raw_with_options(
+        this: &D1PreparedStatementExt,
+        options: &JsValue,
+    ) -> Result<js_sys::Promise, JsValue>;
+}
+
+pub(crate) fn with_session(
+    d1: &D1Database,
+    constraint_or_bookmark: &str,
+) -> Result<D1DatabaseSession, JsValue> {
+    d1.unchecked_ref::<D1DatabaseExt>()
+        .with_session(constraint_or_bookmark)
+}
+
+/// `raw({ columnNames: true })`, resolving to an array of the column names followed by the rows.
+///
+/// ref: <https://developers.cloudflare.com/d1/worker-api/prepared-statements/#raw>
+pub(crate) fn raw_with_column_names(
+    statement: &D1PreparedStatement,
+) -> Result<js_sys::Promise, JsValue> {
+    let options = js_sys::Object::new();
+    js_sys::Reflect::set(&options, &"columnNames".into(), &JsValue::TRUE)?;
+    statement
+        .unchecked_ref::<D1PreparedStatementExt>()
+        .raw_with_options(&options)
+}
+
+/// Common interface of `D1Database` and `D1DatabaseSession`.
+pub(crate) trait D1Binding {
+    fn prepare(&self, sql: &str) -> Result<D1PreparedStatement, JsValue>;
+    fn batch(&self, statements: js_sys::Array) -> Result<js_sys::Promise, JsValue>;
+
+    fn prepare_with(
+        &self,
+        sql: &str,
+        arguments: Option<crate::arguments::D1Arguments>,
+    ) -> Result<D1PreparedStatement, JsValue> {
+        bind(&self.prepare(sql)?, arguments)
+    }
+
+    /// `prepare_with`, but reusing `prepared` if it's given.
+    fn prepare_or_reuse(
+        &self,
+        sql: &str,
+        prepared: Option<&D1PreparedStatement>,
+        arguments: Option<crate::arguments::D1Arguments>,
+    ) -> Result<D1PreparedStatement, JsValue> {
+        match prepared {
+            Some(prepared) => bind(prepared, arguments),
+            None => self.prepare_with(sql, arguments),
+        }
+    }
+}
+
+/// Bind the arguments to the statement.
+///
+/// D1's `bind` returns a new statement leaving the original one unbound,
+/// so a prepared statement can be bound many times.
+pub(crate) fn bind(
+    statement: &D1PreparedStatement,
+    arguments: Option<crate::arguments::D1Arguments>,
+) -> Result<D1PreparedStatement, JsValue> {
+    match arguments {
+        Some(a) => statement.bind(a.as_ref().iter().collect()),
+        None => Ok(statement.clone()),
+    }
+}
+
+impl D1Binding for D1Database {
+    fn prepare(&self, sql: &str) -> Result<D1PreparedStatement, JsValue> {
+        D1Database::prepare(self, sql)
+    }
+    fn batch(&self, statements: js_sys::Array) -> Result<js_sys::Promise, JsValue> {
+        D1Database::batch(self, statements)
+    }
+}
+
+impl D1Binding for D1DatabaseSession {
+    fn prepare(&self, sql: &str) -> Result<D1PreparedStatement, JsValue> {
+        D1DatabaseSession::prepare(self, sql)
+    }
+    fn batch(&self, statements: js_sys::Array) -> Result<js_sys::Promise, JsValue> {
+        D1DatabaseSession::batch(self, statements)
+    }
+}
@@ -1,12 +1,45 @@
 #![cfg_attr(docsrs, feature(doc_cfg))]
 
+#[cfg(not(target_arch = "wasm32"))]
+macro_rules! unreachable_native_impl_of_item_for_only_wasm32 {
+    ($item_for_only_wasm32:literal) => {
+        panic!(
+            "native `{}`: Invalid use of `sqlx_d1`. Be sure to use `sqlx_d1` where the target is set to \
+            `wasm32-unknown-unknown` ! \n\
+            For this, typcally, place `.cargo/config.toml` of following content at the root of \
+            your project or workspace : \n\
+            \n\
+            [build]\n\
+            target = \"wasm32-unknown-unknown\"\n",
+            $item_for_only_wasm32
+        )
+    };
+}
+
 mod arguments;
+mod batch;
 mod column;
 mod connection;
+mod dump;
+mod env;
 mod error;
+mod exec;
+mod ffi;
+mod logger;
+mod migrate;
+mod native;
+mod observer;
+mod pool;
+mod pragma;
 mod query_result;
+mod retry;
 mod row;
+mod schema;
+mod session;
+mod span;
 mod statement;
+#[doc(hidden)]
+pub mod testing;
 mod transaction;
 mod type_info;
 pub mod types;
@@ -15,7 +48,25 @@ mod value;
 type ResultFuture<'a, T> =
     std::pin::Pin<Box<dyn Future<Output = Result<T, sqlx_core::Error>> + Send + 'a>>;
 
+pub use batch::D1BatchResult;
 pub use connection::{D1ConnectOptions, D1Connection};
+pub use env::EnvExt;
+pub use exec::D1ExecResult;
+#[cfg(feature = "migrate")]
+#[cfg_attr(docsrs, doc(cfg(feature = "migrate")))]
+pub use migrate::{D1Migration, D1MigrationDrift, D1Migrator, D1VerifyError};
+pub use observer::{D1QueryEnd, D1QueryObserver, D1QueryStart};
+pub use pool::{D1Pool, D1PoolConnection};
+pub use pragma::{
+    D1ForeignKeyList, D1ForeignKeyViolation, D1IndexList, D1Pragma, D1TableInfo, D1TableList,
+};
+pub use query_result::{D1QueryMeta, D1QueryResult};
+pub use retry::D1RetryPolicy;
+pub use schema::{
+    D1ColumnSchema, D1ForeignKeySchema, D1IndexSchema, D1Schema, D1TableSchema, D1ViewSchema,
+};
+pub use session::{D1Session, D1SessionConstraint};
+pub use statement::D1Statement;
 
 #[derive(Debug)]
 pub struct D1;
@@ -47,12 +98,13 @@ impl sqlx_core::database::Database for D1 {
 }
 
 pub mod query {
-    use crate::{D1, arguments::D1Arguments, row::D1Row};
-    use sqlx_core::from_row::FromRow;
+    use crate::{D1, D1QueryMeta, D1QueryResult, arguments::D1Arguments, row::D1Row};
+    use futures_core::stream::BoxStream;
+    use sqlx_core::{Either, arguments::IntoArguments, executor::Executor, from_row::FromRow};
 
     pub type QueryBuilder<'args> = sqlx_core::query_builder::QueryBuilder<'args, D1>;
 
-    pub use sqlx_core::query::Query;
+    pub use sqlx_core::query::{Map, Query};
     pub fn query(sql: &str) -> Query<'_, D1, D1Arguments> {
         sqlx_core::query::query(sql)
     }
@@ -87,9 +139,154 @@ pub mod query {
     {
         sqlx_core::query_scalar::query_scalar_with(sql, args)
     }
+
+    /// Extension for fetching rows together with D1's execution metadata.
+    ///
+    /// NOTE: D1's `raw` API, by which rows are usually fetched to keep the order and
+    /// duplicates of columns, reports no metadata, so the other `fetch_*` get zeroed
+    /// metadata for statements returning rows. `fetch_all_with_meta` instead runs the
+    /// query once by `all`, which returns rows as JS objects: columns of the same name
+    /// collapse into the last one, and the columns are ordered as the keys of the first
+    /// row object. Alias the columns (`AS`) to keep all of them.
+    ///
+    /// ```rust,ignore
+    /// use sqlx_d1::query::FetchWithMeta;
+    ///
+    /// let (users, meta) = sqlx_d1::query_as!(User, "SELECT * FROM users")
+    ///     .fetch_all_with_meta(&conn)
+    ///     .await?;
+    /// worker::console_log!("{} rows read", meta.rows_read);
+    /// ```
+    pub trait FetchWithMeta<'q>: Sized {
+        type Output;
+
+        fn fetch_all_with_meta<'e, 'c: 'e, E>(
+            self,
+            executor: E,
+        ) -> crate::ResultFuture<'e, (Vec<Self::Output>, D1QueryMeta)>
+        where
+            'q: 'e,
+            Self: 'e,
+            E: 'e + Executor<'c, Database = D1>;
+    }
+
+    async fn collect_with_meta<O>(
+        mut stream: BoxStream<'_, Result<Either<D1QueryResult, O>, sqlx_core::Error>>,
+    ) -> Result<(Vec<O>, D1QueryMeta), sqlx_core::Error> {
+        use futures_util::TryStreamExt as _;
+
+        /* `Map` makes the query stream on the first poll */
+        let (mut rows, mut query_result) = (Vec::new(), D1QueryResult::default());
+        while let Some(step) = futures_util::future::poll_fn(|cx| {
+            crate::connection::with_meta(|| stream.try_poll_next_unpin(cx))
+        })
+        .await
+        .transpose()?
+        {
+            match step {
+                Either::Left(r) => query_result.extend([r]),
+                Either::Right(row) => rows.push(row),
+            }
+        }
+        Ok((rows, query_result.meta))
+    }
+
+    impl<'q, A> FetchWithMeta<'q> for Query<'q, D1, A>
+    where
+        A: 'q + Send + IntoArguments<'q, D1>,
+    {
+        type Output = D1Row;
+
+        fn fetch_all_with_meta<'e, 'c: 'e, E>(
+            self,
+            executor: E,
+        ) -> crate::ResultFuture<'e, (Vec<Self::Output>, D1QueryMeta)>
+        where
+            'q: 'e,
+            Self: 'e,
+            E: 'e + Executor<'c, Database = D1>,
+        {
+            #[allow(deprecated)]
+            Box::pin(collect_with_meta(crate::connection::with_meta(|| {
+                self.fetch_many(executor)
+            })))
+        }
+    }
+
+    impl<'q, O, A> FetchWithMeta<'q> for QueryAs<'q, D1, O, A>
+    where
+        O: Send + Unpin + for<'r> FromRow<'r, D1Row>,
+        A: 'q + Send + IntoArguments<'q, D1>,
+    {
+        type Output = O;
+
+        fn fetch_all_with_meta<'e, 'c: 'e, E>(
+            self,
+            executor: E,
+        ) -> crate::ResultFuture<'e, (Vec<Self::Output>, D1QueryMeta)>
+        where
+            'q: 'e,
+            Self: 'e,
+            E: 'e + Executor<'c, Database = D1>,
+        {
+            #[allow(deprecated)]
+            Box::pin(collect_with_meta(crate::connection::with_meta(|| {
+                self.fetch_many(executor)
+            })))
+        }
+    }
+
+    impl<'q, O, A> FetchWithMeta<'q> for QueryScalar<'q, D1, O, A>
+    where
+        O: Send + Unpin,
+        (O,): Send + Unpin + for<'r> FromRow<'r, D1Row>,
+        A: 'q + Send + IntoArguments<'q, D1>,
+    {
+        type Output = O;
+
+        fn fetch_all_with_meta<'e, 'c: 'e, E>(
+            self,
+            executor: E,
+        ) -> crate::ResultFuture<'e, (Vec<Self::Output>, D1QueryMeta)>
+        where
+            'q: 'e,
+            Self: 'e,
+            E: 'e + Executor<'c, Database = D1>,
+        {
+            #[allow(deprecated)]
+            Box::pin(collect_with_meta(crate::connection::with_meta(|| {
+                self.fetch_many(executor)
+            })))
+        }
+    }
+
+    impl<'q, F, O, A> FetchWithMeta<'q> for Map<'q, D1, F, A>
+    where
+        F: FnMut(D1Row) -> Result<O, sqlx_core::Error> + Send,
+        O: 'q + Send + Unpin,
+        A: 'q + Send + IntoArguments<'q, D1>,
+    {
+        type Output = O;
+
+        fn fetch_all_with_meta<'e, 'c: 'e, E>(
+            self,
+            executor: E,
+        ) -> crate::ResultFuture<'e, (Vec<Self::Output>, D1QueryMeta)>
+        where
+            'q: 'e,
+            Self: 'e,
+            E: 'e + Executor<'c, Database = D1>,
+        {
+            #[allow(deprecated)]
+            Box::pin(collect_with_meta(crate::connection::with_meta(|| {
+                self.fetch_many(executor)
+            })))
+        }
+    }
 }
 pub use query::{
-    QueryBuilder, query, query_as, query_as_with, query_scalar, query_scalar_with, query_with,
+    FetchWithMeta, QueryBuilder, query, query_as, query_as_with, query_scalar, query_scalar_with,
+    query_with,
 };
 
 pub use sqlx_core::Error;
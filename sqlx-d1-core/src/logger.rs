@@ -0,0 +1,108 @@
+//! ref: <https://github.com/launchbadge/sqlx/blob/1c7b3d0751cdca5a08fbfa7f24c985fc3774cf11/sqlx-core/src/logger.rs>
+//!
+//! `sqlx_core::logger::QueryLogger` is not available on Workers because
+//! `std::time::Instant` panics on `wasm32-unknown-unknown`.
+
+use log::LevelFilter;
+use std::time::Duration;
+
+#[derive(Debug, Clone)]
+#[cfg_attr(not(target_arch = "wasm32"), allow(dead_code))]
+pub(crate) struct D1LogSettings {
+    pub(crate) statements_level: LevelFilter,
+    pub(crate) slow_statements_level: LevelFilter,
+    pub(crate) slow_statements_duration: Duration,
+    /// whether to log values of parameters instead of redacting them
+    pub(crate) parameter_values: bool,
+}
+
+impl Default for D1LogSettings {
+    /// Statements are not logged, and ones slower than 1s are logged as warnings.
+    fn default() -> Self {
+        Self {
+            statements_level: LevelFilter::Off,
+            slow_statements_level: LevelFilter::Warn,
+            slow_statements_duration: Duration::from_secs(1),
+            parameter_values: false,
+        }
+    }
+}
+
+#[cfg(target_arch = "wasm32")]
+pub(crate) struct QueryLogger<'a> {
+    sql: &'a str,
+    parameters: String,
+    settings: &'a D1LogSettings,
+    /// milliseconds from `Date.now()`, which advances on I/O like D1 calls in Workers
+    start: f64,
+}
+
+#[cfg(target_arch = "wasm32")]
+impl<'a> QueryLogger<'a> {
+    pub(crate) fn new(
+        sql: &'a str,
+        arguments: Option<&crate::arguments::D1Arguments>,
+        settings: &'a D1LogSettings,
+    ) -> Self {
+        let arguments = arguments.map_or(&[][..], |a| a.as_ref());
+        let parameters = if settings.parameter_values {
+            format!("{arguments:?}")
+        } else {
+            format!("[{}]", vec!["?"; arguments.len()].join(", "))
+        };
+
+        Self {
+            sql,
+            parameters,
+            settings,
+            start: worker::js_sys::Date::now(),
+        }
+    }
+
+    pub(crate) fn finish(&self, rows_returned: usize, rows_affected: usize) {
+        self.log(format_args!(
+            "rows affected: {rows_affected}, rows returned: {rows_returned}"
+        ));
+    }
+
+    /// Failed statements are logged at the same level as the succeeded ones.
+    pub(crate) fn finish_with_error(&self, error: &sqlx_core::Error) {
+        self.log(format_args!("error: {error}"));
+    }
+
+    fn log(&self, outcome: std::fmt::Arguments<'_>) {
+        let elapsed =
+            Duration::from_secs_f64((worker::js_sys::Date::now() - self.start).max(0.) / 1000.);
+
+        let was_slow = elapsed >= self.settings.slow_statements_duration;
+        let level = if was_slow {
+            self.settings.slow_statements_level
+        } else {
+            self.settings.statements_level
+        };
+        let Some(level) = level.to_level() else {
+            return;
+        };
+
+        let summary = sqlx_core::logger::parse_query_summary(self.sql);
+        let message = format!(
+            "{}{summary}{}; parameters: {}, {outcome}, elapsed: {elapsed:.3?}\n\n{}\n",
+            if was_slow { "slow statement: " } else { "" },
+            if summary != self.sql { " …" } else { "" },
+            self.parameters,
+            self.sql,
+        );
+
+        /* fallback to Workers console when no logger is set */
+        if log::max_level() == LevelFilter::Off {
+            match level {
+                log::Level::Error => worker::console_error!("{message}"),
+                log::Level::Warn => worker::console_warn!("{message}"),
+                log::Level::Info => worker::console_log!("{message}"),
+                log::Level::Debug | log::Level::Trace => worker::console_debug!("{message}"),
+            }
+        } else {
+            log::log!(target: "sqlx::query", level, "{message}");
+        }
+    }
+}
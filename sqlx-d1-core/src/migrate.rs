@@ -0,0 +1,700 @@
+//! Migrations compatible with `wrangler d1 migrations`.
+//!
+//! ref: <https://developers.cloudflare.com/d1/reference/migrations/>
+
+#![cfg(feature = "migrate")]
+
+use crate::{D1, D1Connection, D1QueryResult, arguments::D1Arguments, row::D1Row};
+use futures_core::future::BoxFuture;
+use sqlx_core::{
+    migrate::{AppliedMigration, MigrateError, Migration, MigrationType},
+    query::Query,
+    row::Row as _,
+};
+use std::borrow::Cow;
+
+/// The table `wrangler d1 migrations apply` records applied migrations in by default,
+/// configurable by `migrations_table` of wrangler's config.
+const DEFAULT_MIGRATIONS_TABLE: &str = "d1_migrations";
+
+/// Names of the migrations table of wrangler and the one of checksums beside it.
+///
+/// Checksums are kept in `<migrations table>_checksums` keyed by the names, so that
+/// the schema of wrangler's table is left as it is. Only the migrations applied by
+/// sqlx_d1 have their checksums.
+struct Tables {
+    migrations: String,
+    checksums: String,
+}
+
+impl Tables {
+    fn of(migrations_table: &str) -> Self {
+        Self {
+            migrations: crate::pragma::quote_identifier(migrations_table),
+            checksums: crate::pragma::quote_identifier(&format!("{migrations_table}_checksums")),
+        }
+    }
+}
+
+/// A migration file of wrangler's migrations directory.
+#[derive(Debug, Clone)]
+pub struct D1Migration {
+    /// file name like `0001_create_users.sql`, recorded in `d1_migrations`
+    pub name: Cow<'static, str>,
+    pub sql: Cow<'static, str>,
+    /// SHA-384 of `sql`
+    pub checksum: Cow<'static, [u8]>,
+}
+
+impl D1Migration {
+    pub fn new(name: impl Into<Cow<'static, str>>, sql: impl Into<Cow<'static, str>>) -> Self {
+        use sha2::{Digest, Sha384};
+
+        let sql = sql.into();
+        Self {
+            name: name.into(),
+            checksum: Cow::Owned(Sha384::digest(sql.as_bytes()).to_vec()),
+            sql,
+        }
+    }
+
+    /// The leading number of the name, like `1` of `0001_create_users.sql`.
+    pub fn version(&self) -> i64 {
+        version_of(&self.name)
+    }
+}
+
+/// Set of migrations, usually embedded by `sqlx_d1::migrate!`.
+///
+/// ## Example
+///
+/// ```rust,ignore
+/// static MIGRATOR: sqlx_d1::D1Migrator = sqlx_d1::migrate!("migrations");
+///
+/// MIGRATOR.run(&conn).await?;
+/// ```
+#[derive(Debug, Clone)]
+pub struct D1Migrator {
+    /* public for `migrate!` to initialize this in const-promotable context */
+    #[doc(hidden)]
+    pub migrations: Cow<'static, [D1Migration]>,
+    #[doc(hidden)]
+    pub migrations_table: Cow<'static, str>,
+}
+
+impl D1Migrator {
+    pub fn new(mut migrations: Vec<D1Migration>) -> Self {
+        migrations.sort_by(|a, b| a.name.cmp(&b.name));
+        Self {
+            migrations: Cow::Owned(migrations),
+            migrations_table: Cow::Borrowed(DEFAULT_MIGRATIONS_TABLE),
+        }
+    }
+
+    /// Record the migrations in `table` instead of `d1_migrations`, for
+    /// `migrations_table` set in wrangler's config.
+    pub fn migrations_table(mut self, table: impl Into<Cow<'static, str>>) -> Self {
+        self.migrations_table = table.into();
+        self
+    }
+
+    /// Migrations in the order of application.
+    pub fn iter(&self) -> std::slice::Iter<'_, D1Migration> {
+        self.migrations.iter()
+    }
+
+    /// Apply the migrations not yet recorded in the migrations table, each in a batch
+    /// together with its record, like `wrangler d1 migrations apply` does.
+    ///
+    /// Returns the names of the applied migrations.
+    pub async fn run(&self, conn: &D1Connection) -> Result<Vec<String>, MigrateError> {
+        let tables = Tables::of(&self.migrations_table);
+        ensure_migrations_table(conn, &tables).await?;
+
+        let applied = applied_migrations(conn, &self.migrations_table).await?;
+        let mut newly_applied = Vec::new();
+        for migration in self.iter() {
+            if applied.iter().any(|(name, _)| *name == migration.name) {
+                continue;
+            }
+            apply_migration(
+                conn,
+                &tables,
+                &migration.name,
+                &migration.sql,
+                &migration.checksum,
+            )
+            .await
+            .map_err(|e| MigrateError::ExecuteMigration(e, migration.version()))?;
+            newly_applied.push(migration.name.clone().into_owned());
+        }
+        Ok(newly_applied)
+    }
+
+    /// Check that the migrations recorded in the migrations table are exactly the
+    /// embedded ones, for example on the first request of a deployment to detect
+    /// Worker code deployed before `wrangler d1 migrations apply --remote`.
+    ///
+    /// NOTE: `wrangler d1 migrations apply` records no checksum, so migrations
+    /// applied by wrangler are **never** checked for modification. Only the ones
+    /// applied by [`D1Migrator::run`] are. In particular, when migrations are
+    /// applied by `wrangler d1 migrations apply --remote`, `verify` detects missing
+    /// and extra migrations but no modified ones.
+    ///
+    /// ## Example
+    ///
+    /// ```rust,ignore
+    /// if let Err(e) = MIGRATOR.verify(&conn).await {
+    ///     worker::console_error!("{e}");
+    ///     return worker::Response::error("database is not migrated", 503);
+    /// }
+    /// ```
+    pub async fn verify(&self, conn: &D1Connection) -> Result<(), D1VerifyError> {
+        let applied = applied_migrations(conn, &self.migrations_table).await?;
+
+        let drift = diff(&applied, &self.migrations);
+        if drift.missing.is_empty() && drift.extra.is_empty() && drift.modified.is_empty() {
+            Ok(())
+        } else {
+            Err(D1VerifyError::Drift(drift))
+        }
+    }
+}
+
+/// Error of [`D1Migrator::verify`].
+#[derive(Debug)]
+pub enum D1VerifyError {
+    /// failed to read the migrations table
+    Database(sqlx_core::Error),
+    Drift(D1MigrationDrift),
+}
+
+/// Difference between the embedded migrations and the ones recorded in the migrations table.
+#[derive(Debug, Clone, PartialEq)]
+pub struct D1MigrationDrift {
+    /// embedded but not applied
+    pub missing: Vec<String>,
+    /// applied but not embedded
+    pub extra: Vec<String>,
+    /// applied with a checksum different from the embedded one
+    pub modified: Vec<String>,
+}
+
+impl From<sqlx_core::Error> for D1VerifyError {
+    fn from(e: sqlx_core::Error) -> Self {
+        Self::Database(e)
+    }
+}
+
+impl std::fmt::Display for D1VerifyError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Database(e) => write!(f, "failed to read applied migrations: {e}"),
+            Self::Drift(drift) => std::fmt::Display::fmt(drift, f),
+        }
+    }
+}
+impl std::error::Error for D1VerifyError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Self::Database(e) => Some(e),
+            Self::Drift(_) => None,
+        }
+    }
+}
+
+impl std::fmt::Display for D1MigrationDrift {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str("applied migrations differ from the embedded ones:")?;
+        for (label, names) in [
+            ("missing", &self.missing),
+            ("extra", &self.extra),
+            ("modified", &self.modified),
+        ] {
+            if !names.is_empty() {
+                write!(f, " {label} `{}`;", names.join("`, `"))?;
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Difference of the `applied` pairs of name and checksum from the `embedded` migrations.
+/// Applied ones without checksum are not taken as modified.
+fn diff(applied: &[(String, Option<Vec<u8>>)], embedded: &[D1Migration]) -> D1MigrationDrift {
+    D1MigrationDrift {
+        missing: embedded
+            .iter()
+            .filter(|m| !applied.iter().any(|(name, _)| *name == m.name))
+            .map(|m| m.name.clone().into_owned())
+            .collect(),
+        extra: applied
+            .iter()
+            .filter(|(name, _)| !embedded.iter().any(|m| m.name == *name))
+            .map(|(name, _)| name.clone())
+            .collect(),
+        modified: applied
+            .iter()
+            .filter(|(name, checksum)| {
+                embedded.iter().any(|m| {
+                    m.name == *name && checksum.as_ref().is_some_and(|c| *c != *m.checksum)
+                })
+            })
+            .map(|(name, _)| name.clone())
+            .collect(),
+    }
+}
+
+fn version_of(name: &str) -> i64 {
+    name.split(|c: char| !c.is_ascii_digit())
+        .next()
+        .and_then(|digits| digits.parse().ok())
+        .unwrap_or_default()
+}
+
+/// Create the migrations table with the same schema as wrangler does,
+/// and the checksums table beside it.
+async fn ensure_migrations_table(
+    conn: &D1Connection,
+    tables: &Tables,
+) -> Result<(), sqlx_core::Error> {
+    let Tables {
+        migrations,
+        checksums,
+    } = tables;
+    conn.batch([
+        crate::query(&format!(
+            "CREATE TABLE IF NOT EXISTS {migrations} (\
+            id INTEGER PRIMARY KEY AUTOINCREMENT, \
+            name TEXT UNIQUE, \
+            applied_at TIMESTAMP DEFAULT CURRENT_TIMESTAMP NOT NULL\
+            )"
+        )),
+        crate::query(&format!(
+            "CREATE TABLE IF NOT EXISTS {checksums} (\
+            name TEXT PRIMARY KEY, \
+            checksum BLOB NOT NULL\
+            )"
+        )),
+    ])
+    .await?;
+    Ok(())
+}
+
+/// Pairs of name and checksum (`None` if applied by wrangler) in the order of application.
+/// Empty if the migrations table doesn't exist.
+async fn applied_migrations(
+    conn: &D1Connection,
+    migrations_table: &str,
+) -> Result<Vec<(String, Option<Vec<u8>>)>, sqlx_core::Error> {
+    let pragma = conn.pragma();
+    if pragma.table_info(migrations_table).await?.is_empty() {
+        return Ok(Vec::new());
+    }
+    let Tables {
+        migrations,
+        checksums,
+    } = Tables::of(migrations_table);
+    let sql = if pragma
+        .table_info(&format!("{migrations_table}_checksums"))
+        .await?
+        .is_empty()
+    {
+        /* created by wrangler */
+        format!("SELECT name, NULL AS checksum FROM {migrations} ORDER BY id")
+    } else {
+        format!(
+            "SELECT m.name AS name, c.checksum AS checksum \
+            FROM {migrations} AS m LEFT JOIN {checksums} AS c ON c.name = m.name \
+            ORDER BY m.id"
+        )
+    };
+
+    crate::query(&sql)
+        .fetch_all(conn)
+        .await?
+        .into_iter()
+        .map(|row: D1Row| Ok((row.try_get("name")?, row.try_get("checksum")?)))
+        .collect()
+}
+
+/// Execute the statements of a migration and the ones recording it in a batch.
+async fn execute_migration<'q>(
+    conn: &D1Connection,
+    statements: &'q [String],
+    records: impl IntoIterator<Item = Query<'q, D1, D1Arguments>>,
+) -> Result<Vec<D1QueryResult>, sqlx_core::Error> {
+    /* collected before awaiting, to keep the future `Send` */
+    let queries = statements
+        .iter()
+        .map(|statement| crate::query(statement))
+        .chain(records)
+        .collect::<Vec<_>>();
+    let results = conn.batch(queries).await?;
+    Ok(results.into_iter().map(|r| r.query_result).collect())
+}
+
+async fn apply_migration(
+    conn: &D1Connection,
+    tables: &Tables,
+    name: &str,
+    sql: &str,
+    checksum: &[u8],
+) -> Result<Vec<D1QueryResult>, sqlx_core::Error> {
+    let statements = crate::statement::split_statements(sql);
+    let records = [
+        format!("INSERT INTO {} (name) VALUES (?)", tables.migrations),
+        format!(
+            "INSERT OR REPLACE INTO {} (name, checksum) VALUES (?, ?)",
+            tables.checksums
+        ),
+    ];
+    execute_migration(
+        conn,
+        &statements,
+        [
+            crate::query(&records[0]).bind(name),
+            crate::query(&records[1]).bind(name).bind(checksum),
+        ],
+    )
+    .await
+}
+
+/// Name of `d1_migrations` for a migration resolved by sqlx, whose version has lost
+/// the zero padding of the file name and description has `_`s replaced with spaces.
+///
+/// The name already recorded for the version is used if any. Otherwise the version
+/// is padded as wide as the recorded ones, or 4 digits like wrangler names by default.
+fn wrangler_name_of(migration: &Migration, applied: &[(String, Option<Vec<u8>>)]) -> String {
+    if let Some((name, _)) = applied
+        .iter()
+        .find(|(name, _)| version_of(name) == migration.version)
+    {
+        return name.clone();
+    }
+
+    let width = applied.first().map_or(4, |(name, _)| {
+        name.chars().take_while(char::is_ascii_digit).count()
+    });
+    format!(
+        "{:0width$}_{}.sql",
+        migration.version,
+        migration.description.replace(' ', "_")
+    )
+}
+
+/// Total duration D1 reports.
+fn duration_of(results: &[D1QueryResult]) -> std::time::Duration {
+    std::time::Duration::from_secs_f64(results.iter().map(|r| r.meta.duration).sum::<f64>() / 1000.)
+}
+
+#[cfg(target_arch = "wasm32")]
+fn boxed<'a, T>(future: impl Future<Output = T> + 'a) -> BoxFuture<'a, T> {
+    /* SAFETY: used in single-threaded Workers */
+    Box::pin(worker::send::SendFuture::new(future))
+}
+#[cfg(not(target_arch = "wasm32"))]
+fn boxed<'a, T>(future: impl Future<Output = T> + Send + 'a) -> BoxFuture<'a, T> {
+    Box::pin(future)
+}
+
+/// Records in `d1_migrations` the same way as [`D1Migrator`], so that
+/// `sqlx_core::migrate::Migrator` and wrangler can be used interchangeably.
+///
+/// NOTE: `sqlx_core::migrate::Migrator` has no way to configure the table, so
+/// this always uses `d1_migrations`. Use [`D1Migrator::migrations_table`] for
+/// `migrations_table` set in wrangler's config.
+///
+/// NOTE: migrations applied by wrangler have no checksum recorded, and
+/// `sqlx_core::migrate::Migrator` compares checksums of all the applied ones.
+/// So it reports them as modified, where [`D1Migrator`] is to be used.
+impl sqlx_core::migrate::Migrate for D1Connection {
+    fn ensure_migrations_table(&mut self) -> BoxFuture<'_, Result<(), MigrateError>> {
+        boxed(async move {
+            Ok(ensure_migrations_table(self, &Tables::of(DEFAULT_MIGRATIONS_TABLE)).await?)
+        })
+    }
+
+    /// Always `None` as each migration is applied atomically in a batch.
+    fn dirty_version(&mut self) -> BoxFuture<'_, Result<Option<i64>, MigrateError>> {
+        boxed(async { Ok(None) })
+    }
+
+    fn list_applied_migrations(
+        &mut self,
+    ) -> BoxFuture<'_, Result<Vec<AppliedMigration>, MigrateError>> {
+        boxed(async move {
+            Ok(applied_migrations(self, DEFAULT_MIGRATIONS_TABLE)
+                .await?
+                .into_iter()
+                .map(|(name, checksum)| AppliedMigration {
+                    version: version_of(&name),
+                    checksum: Cow::Owned(checksum.unwrap_or_default()),
+                })
+                .collect())
+        })
+    }
+
+    /// No-op as D1 has no lock.
+    fn lock(&mut self) -> BoxFuture<'_, Result<(), MigrateError>> {
+        boxed(async { Ok(()) })
+    }
+
+    /// No-op as D1 has no lock.
+    fn unlock(&mut self) -> BoxFuture<'_, Result<(), MigrateError>> {
+        boxed(async { Ok(()) })
+    }
+
+    fn apply<'e: 'm, 'm>(
+        &'e mut self,
+        migration: &'m Migration,
+    ) -> BoxFuture<'m, Result<std::time::Duration, MigrateError>> {
+        boxed(async move {
+            let results = async {
+                let applied = applied_migrations(self, DEFAULT_MIGRATIONS_TABLE).await?;
+                let name = wrangler_name_of(migration, &applied);
+                let tables = Tables::of(DEFAULT_MIGRATIONS_TABLE);
+                apply_migration(self, &tables, &name, &migration.sql, &migration.checksum).await
+            }
+            .await
+            .map_err(|e| MigrateError::ExecuteMigration(e, migration.version))?;
+            Ok(duration_of(&results))
+        })
+    }
+
+    fn revert<'e: 'm, 'm>(
+        &'e mut self,
+        migration: &'m Migration,
+    ) -> BoxFuture<'m, Result<std::time::Duration, MigrateError>> {
+        boxed(async move {
+            debug_assert!(matches!(
+                migration.migration_type,
+                MigrationType::ReversibleDown
+            ));
+
+            let results = async {
+                let applied = applied_migrations(self, DEFAULT_MIGRATIONS_TABLE).await?;
+                let name = wrangler_name_of(migration, &applied);
+                let statements = crate::statement::split_statements(&migration.sql);
+                let Tables {
+                    migrations,
+                    checksums,
+                } = Tables::of(DEFAULT_MIGRATIONS_TABLE);
+                let records = [
+                    format!("DELETE FROM {migrations} WHERE name = ?"),
+                    format!("DELETE FROM {checksums} WHERE name = ?"),
+                ];
+                execute_migration(
+                    self,
+                    &statements,
+                    [
+                        crate::query(&records[0]).bind(name.clone()),
+                        crate::query(&records[1]).bind(name),
+                    ],
+                )
+                .await
+            }
+            .await
+            .map_err(|e| MigrateError::ExecuteMigration(e, migration.version))?;
+            Ok(duration_of(&results))
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{D1Migration, D1MigrationDrift, diff};
+
+    fn embedded() -> [D1Migration; 2] {
+        [
+            D1Migration::new("0001_create_users.sql", "CREATE TABLE users (id INTEGER);"),
+            D1Migration::new("0002_create_posts.sql", "CREATE TABLE posts (id INTEGER);"),
+        ]
+    }
+
+    fn applied(migration: &D1Migration) -> (String, Option<Vec<u8>>) {
+        (
+            migration.name.clone().into_owned(),
+            Some(migration.checksum.to_vec()),
+        )
+    }
+
+    #[test]
+    fn diff_nothing() {
+        let embedded = embedded();
+        assert_eq!(
+            diff(&[applied(&embedded[0]), applied(&embedded[1])], &embedded),
+            D1MigrationDrift {
+                missing: vec![],
+                extra: vec![],
+                modified: vec![],
+            }
+        );
+    }
+
+    #[test]
+    fn diff_missing() {
+        let embedded = embedded();
+        assert_eq!(
+            diff(&[applied(&embedded[0])], &embedded),
+            D1MigrationDrift {
+                missing: vec!["0002_create_posts.sql".into()],
+                extra: vec![],
+                modified: vec![],
+            }
+        );
+        assert_eq!(diff(&[], &embedded).missing.len(), 2);
+    }
+
+    #[test]
+    fn diff_extra() {
+        let embedded = embedded();
+        assert_eq!(
+            diff(
+                &[
+                    applied(&embedded[0]),
+                    applied(&embedded[1]),
+                    ("0003_create_tags.sql".into(), None),
+                ],
+                &embedded
+            ),
+            D1MigrationDrift {
+                missing: vec![],
+                extra: vec!["0003_create_tags.sql".into()],
+                modified: vec![],
+            }
+        );
+    }
+
+    #[test]
+    fn diff_modified() {
+        let embedded = embedded();
+        let edited = D1Migration::new(
+            "0002_create_posts.sql",
+            "CREATE TABLE posts (id INTEGER, title TEXT);",
+        );
+        assert_eq!(
+            diff(&[applied(&embedded[0]), applied(&edited)], &embedded),
+            D1MigrationDrift {
+                missing: vec![],
+                extra: vec![],
+                modified: vec!["0002_create_posts.sql".into()],
+            }
+        );
+    }
+
+    #[test]
+    fn diff_without_checksums_of_wrangler() {
+        let embedded = embedded();
+        assert_eq!(
+            diff(
+                &[
+                    ("0001_create_users.sql".into(), None),
+                    ("0002_create_posts.sql".into(), None),
+                ],
+                &embedded
+            ),
+            D1MigrationDrift {
+                missing: vec![],
+                extra: vec![],
+                modified: vec![],
+            }
+        );
+    }
+
+    #[cfg(not(target_arch = "wasm32"))]
+    mod native {
+        use super::*;
+        use crate::migrate::{D1Migrator, D1VerifyError};
+        use crate::{D1ConnectOptions, D1Connection};
+
+        async fn columns_of(conn: &D1Connection, table: &str) -> Vec<String> {
+            conn.pragma()
+                .table_info(table)
+                .await
+                .unwrap()
+                .into_iter()
+                .map(|column| column.name)
+                .collect()
+        }
+
+        #[test]
+        fn run_records_checksums_beside_migrations_table() {
+            futures_lite::future::block_on(async {
+                let conn = D1ConnectOptions::sqlite_in_memory()
+                    .connect()
+                    .await
+                    .unwrap();
+                let migrator = D1Migrator::new(embedded().to_vec());
+
+                assert_eq!(
+                    migrator.run(&conn).await.unwrap(),
+                    ["0001_create_users.sql", "0002_create_posts.sql"]
+                );
+                assert!(migrator.run(&conn).await.unwrap().is_empty());
+                migrator.verify(&conn).await.unwrap();
+
+                assert_eq!(
+                    columns_of(&conn, "d1_migrations").await,
+                    ["id", "name", "applied_at"]
+                );
+                assert_eq!(
+                    columns_of(&conn, "d1_migrations_checksums").await,
+                    ["name", "checksum"]
+                );
+
+                let edited = D1Migrator::new(vec![
+                    embedded()[0].clone(),
+                    D1Migration::new("0002_create_posts.sql", "CREATE TABLE posts (id TEXT);"),
+                ]);
+                assert!(matches!(
+                    edited.verify(&conn).await,
+                    Err(D1VerifyError::Drift(D1MigrationDrift { modified, .. }))
+                        if modified == ["0002_create_posts.sql"]
+                ));
+            });
+        }
+
+        #[test]
+        fn run_leaves_wrangler_applied_migrations_as_they_are() {
+            futures_lite::future::block_on(async {
+                let conn = D1ConnectOptions::sqlite_in_memory()
+                    .connect()
+                    .await
+                    .unwrap();
+                /* as `wrangler d1 migrations apply` does */
+                conn.exec_script(
+                    "CREATE TABLE migrations (\
+                    id INTEGER PRIMARY KEY AUTOINCREMENT, \
+                    name TEXT UNIQUE, \
+                    applied_at TIMESTAMP DEFAULT CURRENT_TIMESTAMP NOT NULL);\n\
+                    CREATE TABLE users (id INTEGER);\n\
+                    INSERT INTO migrations (name) VALUES ('0001_create_users.sql');",
+                )
+                .await
+                .unwrap();
+
+                let migrator = D1Migrator::new(embedded().to_vec()).migrations_table("migrations");
+                migrator.verify(&conn).await.unwrap_err();
+                assert_eq!(
+                    migrator.run(&conn).await.unwrap(),
+                    ["0002_create_posts.sql"]
+                );
+                migrator.verify(&conn).await.unwrap();
+
+                assert_eq!(
+                    columns_of(&conn, "migrations").await,
+                    ["id", "name", "applied_at"]
+                );
+                assert!(columns_of(&conn, "d1_migrations").await.is_empty());
+                assert_eq!(
+                    crate::query_scalar::<String>("SELECT name FROM migrations_checksums")
+                        .fetch_all(&conn)
+                        .await
+                        .unwrap(),
+                    ["0002_create_posts.sql"]
+                );
+            });
+        }
+    }
+}
@@ -0,0 +1,115 @@
+//! Executing queries on native targets, where `D1Connection` is backed by
+//! `sqlx_sqlite::SqliteConnection` for tests and compile-time verification.
+
+#![cfg(not(target_arch = "wasm32"))]
+
+use crate::{
+    D1, D1Connection, arguments::D1Arguments, observer::Observation, query_result::D1QueryResult,
+    row::D1Row, value::RawValue,
+};
+use sqlx_core::{Either, executor::Execute};
+
+type Step = Either<D1QueryResult, D1Row>;
+
+/// Execute the query on the SQLite database `conn` is connected to.
+pub(crate) async fn fetch_all<'q>(
+    conn: &D1Connection,
+    mut query: impl Execute<'q, D1>,
+) -> Result<Vec<Step>, sqlx_core::Error> {
+    conn.ensure_open()?;
+    let arguments = crate::statement::take_checked_arguments(&mut query)?;
+    let sql = query.sql();
+    let observation = Observation::start(&conn.settings.observers, sql, arguments.as_ref());
+
+    crate::span::instrument(sql, async move {
+        let steps = async {
+            let mut sqlite = conn.inner.lock().await;
+            execute_on(&mut sqlite, sql, arguments).await
+        }
+        .await
+        .inspect_err(|e| {
+            crate::span::record_error(e);
+            observation.finish(0, Err(e));
+        })?;
+
+        /* native results have no metadata but the counts, so just summing them up */
+        let query_result = steps.iter().filter_map(|step| step.as_ref().left()).fold(
+            D1QueryResult::default(),
+            |sum, r| D1QueryResult {
+                rows_affected: sum.rows_affected + r.rows_affected,
+                last_insert_row_id: r.last_insert_row_id,
+                ..Default::default()
+            },
+        );
+        let rows_returned = steps.iter().filter(|step| step.is_right()).count();
+        crate::span::record(rows_returned, &query_result);
+        observation.finish(rows_returned, Ok(&query_result));
+        Ok(steps)
+    })
+    .await
+}
+
+pub(crate) async fn execute_on(
+    sqlite: &mut sqlx_sqlite::SqliteConnection,
+    sql: &str,
+    arguments: Option<D1Arguments>,
+) -> Result<Vec<Step>, sqlx_core::Error> {
+    use futures_util::{StreamExt as _, TryStreamExt as _};
+    use sqlx_core::executor::Executor as _;
+
+    /* shared by the rows of each result set, reset by the result of each statement */
+    let mut columns = None;
+    sqlite
+        .fetch_many(sqlx_core::query::query_with(
+            sql,
+            sqlite_arguments(arguments)?,
+        ))
+        .map(|step| match step? {
+            Either::Left(sqlite_result) => {
+                columns = None;
+                Ok(Either::Left(D1QueryResult {
+                    rows_affected: sqlite_result.rows_affected() as usize,
+                    last_insert_row_id: sqlite_result.last_insert_rowid(),
+                    ..Default::default()
+                }))
+            }
+            Either::Right(sqlite_row) => {
+                D1Row::from_sqlite(&sqlite_row, &mut columns).map(Either::Right)
+            }
+        })
+        .try_collect()
+        .await
+}
+
+/// Bind the values as D1 does: booleans as integers, and arrays of bytes as blobs.
+fn sqlite_arguments<'q>(
+    arguments: Option<D1Arguments>,
+) -> Result<sqlx_sqlite::SqliteArguments<'q>, sqlx_core::Error> {
+    use sqlx_core::arguments::Arguments as _;
+
+    let mut sqlite_arguments = sqlx_sqlite::SqliteArguments::default();
+    for value in arguments.as_ref().map_or(&[][..], |a| a.as_ref()) {
+        match value {
+            RawValue::Null => sqlite_arguments.add(None::<i64>),
+            RawValue::Bool(b) => sqlite_arguments.add(i64::from(*b)),
+            RawValue::Number(n) => match n.as_i64() {
+                Some(i) => sqlite_arguments.add(i),
+                None => sqlite_arguments.add(n.as_f64()),
+            },
+            RawValue::String(s) => sqlite_arguments.add(s.clone()),
+            RawValue::Array(bytes) => match bytes
+                .iter()
+                .map(|b| b.as_u64().and_then(|b| u8::try_from(b).ok()))
+                .collect::<Option<Vec<u8>>>()
+            {
+                Some(bytes) => sqlite_arguments.add(bytes),
+                None => Err(format!("D1 doesn't support binding arrays: `{value}`").into()),
+            },
+            RawValue::Object(_) => {
+                Err(format!("D1 doesn't support binding objects: `{value}`").into())
+            }
+        }
+        .map_err(sqlx_core::Error::Encode)?;
+    }
+    Ok(sqlite_arguments)
+}
@@ -0,0 +1,167 @@
+use crate::query_result::D1QueryResult;
+use std::time::Duration;
+
+/// Observer called before and after each query executed on [`D1Connection`](crate::D1Connection)
+/// or [`D1Session`](crate::D1Session), registered by
+/// [`D1ConnectOptions::observer`](crate::D1ConnectOptions::observer) or
+/// [`D1Connection::observer`](crate::D1Connection::observer).
+///
+/// ## Example
+///
+/// ```rust,ignore
+/// #[derive(Default)]
+/// struct QueryCounter(std::cell::Cell<usize>);
+///
+/// impl sqlx_d1::D1QueryObserver for QueryCounter {
+///     fn after_query(&self, _: &sqlx_d1::D1QueryEnd<'_>) {
+///         self.0.set(self.0.get() + 1);
+///     }
+/// }
+///
+/// let counter = std::rc::Rc::new(QueryCounter::default());
+/// let conn = sqlx_d1::D1Connection::new(env.d1("DB")?).observer(counter.clone());
+/// ```
+///
+/// On native targets, where connections can be shared across threads, observers
+/// are also required to be `Send + Sync`.
+pub trait D1QueryObserver: ThreadSafety + 'static {
+    fn before_query(&self, #[allow(unused)] query: &D1QueryStart<'_>) {}
+    fn after_query(&self, #[allow(unused)] query: &D1QueryEnd<'_>) {}
+}
+
+/// `Send + Sync` on native targets, and nothing on Workers, which are single-threaded.
+#[cfg(target_arch = "wasm32")]
+pub trait ThreadSafety {}
+#[cfg(target_arch = "wasm32")]
+impl<T: ?Sized> ThreadSafety for T {}
+#[cfg(not(target_arch = "wasm32"))]
+pub trait ThreadSafety: Send + Sync {}
+#[cfg(not(target_arch = "wasm32"))]
+impl<T: Send + Sync + ?Sized> ThreadSafety for T {}
+
+#[cfg(target_arch = "wasm32")]
+impl<O: D1QueryObserver + ?Sized> D1QueryObserver for std::rc::Rc<O> {
+    fn before_query(&self, query: &D1QueryStart<'_>) {
+        (**self).before_query(query)
+    }
+    fn after_query(&self, query: &D1QueryEnd<'_>) {
+        (**self).after_query(query)
+    }
+}
+
+impl<O: D1QueryObserver + ?Sized> D1QueryObserver for std::sync::Arc<O> {
+    fn before_query(&self, query: &D1QueryStart<'_>) {
+        (**self).before_query(query)
+    }
+    fn after_query(&self, query: &D1QueryEnd<'_>) {
+        (**self).after_query(query)
+    }
+}
+
+/// A query about to be executed.
+#[derive(Debug)]
+#[non_exhaustive]
+pub struct D1QueryStart<'a> {
+    pub sql: &'a str,
+    /// number of the bound arguments
+    pub arguments: usize,
+}
+
+/// A query finished.
+#[derive(Debug)]
+#[non_exhaustive]
+pub struct D1QueryEnd<'a> {
+    pub sql: &'a str,
+    /// number of the bound arguments
+    pub arguments: usize,
+    /// elapsed time measured in the Worker, including retries
+    pub elapsed: Duration,
+    /// number of the returned rows
+    pub rows_returned: usize,
+    /// the result metadata, or the error
+    pub result: Result<&'a D1QueryResult, &'a sqlx_core::Error>,
+}
+
+#[cfg(target_arch = "wasm32")]
+type SharedObserver = std::rc::Rc<dyn D1QueryObserver>;
+#[cfg(not(target_arch = "wasm32"))]
+type SharedObserver = std::sync::Arc<dyn D1QueryObserver>;
+
+/// Registered observers, shared by clones of the connection.
+#[derive(Clone, Default)]
+pub(crate) struct Observers(Vec<SharedObserver>);
+
+impl Observers {
+    pub(crate) fn push(&mut self, observer: impl D1QueryObserver) {
+        #[cfg(target_arch = "wasm32")]
+        self.0.push(std::rc::Rc::new(observer));
+        #[cfg(not(target_arch = "wasm32"))]
+        self.0.push(std::sync::Arc::new(observer));
+    }
+}
+
+impl std::fmt::Debug for Observers {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "Observers({})", self.0.len())
+    }
+}
+
+pub(crate) struct Observation<'a> {
+    observers: &'a [SharedObserver],
+    sql: &'a str,
+    arguments: usize,
+    /// milliseconds from `Date.now()`, which advances on I/O like D1 calls in Workers
+    #[cfg(target_arch = "wasm32")]
+    start: f64,
+    #[cfg(not(target_arch = "wasm32"))]
+    start: std::time::Instant,
+}
+
+impl<'a> Observation<'a> {
+    pub(crate) fn start(
+        Observers(observers): &'a Observers,
+        sql: &'a str,
+        arguments: Option<&crate::arguments::D1Arguments>,
+    ) -> Self {
+        let arguments = arguments.map_or(0, |a| a.as_ref().len());
+        for observer in observers {
+            observer.before_query(&D1QueryStart { sql, arguments });
+        }
+        Self {
+            observers,
+            sql,
+            arguments,
+            #[cfg(target_arch = "wasm32")]
+            start: worker::js_sys::Date::now(),
+            #[cfg(not(target_arch = "wasm32"))]
+            start: std::time::Instant::now(),
+        }
+    }
+
+    pub(crate) fn finish(
+        &self,
+        rows_returned: usize,
+        result: Result<&D1QueryResult, &sqlx_core::Error>,
+    ) {
+        if self.observers.is_empty() {
+            return;
+        }
+
+        #[cfg(target_arch = "wasm32")]
+        let elapsed =
+            Duration::from_secs_f64((worker::js_sys::Date::now() - self.start).max(0.) / 1000.);
+        #[cfg(not(target_arch = "wasm32"))]
+        let elapsed = self.start.elapsed();
+
+        let end = D1QueryEnd {
+            sql: self.sql,
+            arguments: self.arguments,
+            elapsed,
+            rows_returned,
+            result,
+        };
+        for observer in self.observers {
+            observer.after_query(&end);
+        }
+    }
+}
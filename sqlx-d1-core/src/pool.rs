@@ -0,0 +1,303 @@
+//! `sqlx::Pool` internally requires Rust async runtime and timers, which are not
+//! available on Cloudflare Workers. D1 has no connection to pool in the first place,
+//! so [`D1Pool`] just hands out clones of a `D1Connection`.
+
+use crate::{D1, D1Connection};
+use futures_core::future::BoxFuture;
+use sqlx_core::{Either, transaction::Transaction};
+
+/// Pool-like handle of [`D1Connection`], for code written against
+/// `Acquire` or `&Pool` executors.
+///
+/// `acquire` hands out a clone of the connection. Both `&D1Pool` and
+/// `&mut D1Pool` implement `Acquire`:
+///
+/// - `begin` of `&mut D1Pool` starts a transaction on the connection itself.
+/// - `begin` of `&D1Pool` starts one on a clone of the connection, which the pool
+///   keeps until it's dropped, because a `Transaction` can own only a connection
+///   of `sqlx::Pool` (requiring an async runtime) and otherwise has to borrow one.
+///   So for many transactions on a long-lived pool, `&mut D1Pool` is preferred.
+///
+/// ## Example
+///
+/// ```rust,ignore
+/// use sqlx_d1::sqlx_core::acquire::Acquire;
+///
+/// async fn rename_user<'a>(
+///     conn: impl Acquire<'a, Database = sqlx_d1::D1>,
+///     id: i64,
+///     name: &str,
+/// ) -> Result<(), sqlx_d1::Error> {
+///     let mut tx = conn.begin().await?;
+///     sqlx_d1::query!("UPDATE users SET name = ? WHERE id = ?", name, id)
+///         .execute(&mut *tx)
+///         .await?;
+///     tx.commit().await
+/// }
+///
+/// let pool = sqlx_d1::D1Pool::new(sqlx_d1::D1Connection::new(env.d1("DB")?));
+/// rename_user(&pool, 1, "alice").await?;
+/// ```
+pub struct D1Pool {
+    conn: D1Connection,
+    /// clones of `conn` lent to the transactions begun by `&D1Pool`,
+    /// each from `Box::into_raw` and freed on drop
+    lent: std::sync::Mutex<Vec<*mut D1Connection>>,
+}
+
+/* SAFETY: `lent` just owns the boxed connections, as `Vec<Box<D1Connection>>` */
+unsafe impl Send for D1Pool {}
+unsafe impl Sync for D1Pool {}
+
+/// A connection acquired from [`D1Pool`].
+#[derive(Debug)]
+pub struct D1PoolConnection(D1Connection);
+
+impl D1Pool {
+    pub fn new(conn: D1Connection) -> Self {
+        Self {
+            conn,
+            lent: Default::default(),
+        }
+    }
+
+    pub async fn acquire(&self) -> Result<D1PoolConnection, sqlx_core::Error> {
+        self.conn.ensure_open()?;
+        Ok(D1PoolConnection(self.conn.clone()))
+    }
+
+    pub async fn begin(&mut self) -> Result<Transaction<'_, D1>, sqlx_core::Error> {
+        Transaction::begin(&mut self.conn, None).await
+    }
+
+    /// Begin a transaction on a clone of the connection, kept until the pool is dropped.
+    fn begin_lent(&self) -> BoxFuture<'_, Result<Transaction<'_, D1>, sqlx_core::Error>> {
+        if let Err(e) = self.conn.ensure_open() {
+            return Box::pin(async { Err(e) });
+        }
+        let conn = Box::into_raw(Box::new(self.conn.clone()));
+        self.lent
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+            .push(conn);
+        // SAFETY: `conn` is referred to by nothing else, and is freed only on drop of `self`
+        Transaction::begin(unsafe { &mut *conn }, None)
+    }
+}
+
+impl Clone for D1Pool {
+    fn clone(&self) -> Self {
+        Self::new(self.conn.clone())
+    }
+}
+
+impl std::fmt::Debug for D1Pool {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("D1Pool").field("conn", &self.conn).finish()
+    }
+}
+
+impl Drop for D1Pool {
+    fn drop(&mut self) {
+        let lent = self
+            .lent
+            .get_mut()
+            .unwrap_or_else(|poisoned| poisoned.into_inner());
+        for conn in lent.drain(..) {
+            // SAFETY: the transactions borrowing them have ended with the borrow of `self`
+            drop(unsafe { Box::from_raw(conn) });
+        }
+    }
+}
+
+impl From<D1Connection> for D1Pool {
+    fn from(conn: D1Connection) -> Self {
+        Self::new(conn)
+    }
+}
+
+impl std::ops::Deref for D1PoolConnection {
+    type Target = D1Connection;
+
+    fn deref(&self) -> &Self::Target {
+        &self.0
+    }
+}
+impl std::ops::DerefMut for D1PoolConnection {
+    fn deref_mut(&mut self) -> &mut Self::Target {
+        &mut self.0
+    }
+}
+
+impl<'c> sqlx_core::acquire::Acquire<'c> for &'c mut D1Pool {
+    type Database = D1;
+
+    type Connection = D1PoolConnection;
+
+    fn acquire(self) -> BoxFuture<'c, Result<Self::Connection, sqlx_core::Error>> {
+        Box::pin(D1Pool::acquire(self))
+    }
+
+    fn begin(self) -> BoxFuture<'c, Result<Transaction<'c, D1>, sqlx_core::Error>> {
+        Box::pin(D1Pool::begin(self))
+    }
+}
+
+impl<'c> sqlx_core::acquire::Acquire<'c> for &'c D1Pool {
+    type Database = D1;
+
+    type Connection = D1PoolConnection;
+
+    fn acquire(self) -> BoxFuture<'c, Result<Self::Connection, sqlx_core::Error>> {
+        Box::pin(D1Pool::acquire(self))
+    }
+
+    fn begin(self) -> BoxFuture<'c, Result<Transaction<'c, D1>, sqlx_core::Error>> {
+        self.begin_lent()
+    }
+}
+
+sqlx_core::impl_acquire!(D1, D1Connection);
+
+impl<'p> sqlx_core::executor::Executor<'p> for &'p D1Pool {
+    type Database = D1;
+
+    fn fetch_many<'e, 'q: 'e, E>(
+        self,
+        query: E,
+    ) -> futures_core::stream::BoxStream<
+        'e,
+        Result<
+            Either<
+                <Self::Database as sqlx_core::database::Database>::QueryResult,
+                <Self::Database as sqlx_core::database::Database>::Row,
+            >,
+            sqlx_core::Error,
+        >,
+    >
+    where
+        'p: 'e,
+        E: 'q + sqlx_core::executor::Execute<'q, Self::Database>,
+    {
+        <&'p D1Connection as sqlx_core::executor::Executor<'p>>::fetch_many(&self.conn, query)
+    }
+
+    fn fetch_optional<'e, 'q: 'e, E>(
+        self,
+        query: E,
+    ) -> crate::ResultFuture<'e, Option<<Self::Database as sqlx_core::database::Database>::Row>>
+    where
+        'p: 'e,
+        E: 'q + sqlx_core::executor::Execute<'q, Self::Database>,
+    {
+        <&'p D1Connection as sqlx_core::executor::Executor<'p>>::fetch_optional(&self.conn, query)
+    }
+
+    fn prepare_with<'e, 'q: 'e>(
+        self,
+        sql: &'q str,
+        parameters: &'e [<Self::Database as sqlx_core::database::Database>::TypeInfo],
+    ) -> crate::ResultFuture<'e, <Self::Database as sqlx_core::database::Database>::Statement<'q>>
+    where
+        'p: 'e,
+    {
+        <&'p D1Connection as sqlx_core::executor::Executor<'p>>::prepare_with(
+            &self.conn, sql, parameters,
+        )
+    }
+
+    fn describe<'e, 'q: 'e>(
+        self,
+        sql: &'q str,
+    ) -> crate::ResultFuture<'e, sqlx_core::describe::Describe<Self::Database>>
+    where
+        'p: 'e,
+    {
+        <&'p D1Connection as sqlx_core::executor::Executor<'p>>::describe(&self.conn, sql)
+    }
+}
+
+#[cfg(all(test, not(target_arch = "wasm32")))]
+mod tests {
+    use super::*;
+    use crate::D1ConnectOptions;
+    use sqlx_core::{acquire::Acquire, executor::Executor};
+
+    async fn pool() -> D1Pool {
+        let conn = D1ConnectOptions::sqlite_in_memory()
+            .connect()
+            .await
+            .unwrap();
+        conn.exec_script(
+            "CREATE TABLE users (id INTEGER PRIMARY KEY, name TEXT NOT NULL);
+            INSERT INTO users (id, name) VALUES (1, 'anonymous');",
+        )
+        .await
+        .unwrap();
+        D1Pool::new(conn)
+    }
+
+    async fn rename_user<'a>(
+        conn: impl Acquire<'a, Database = D1>,
+        name: &str,
+        commit: bool,
+    ) -> Result<(), sqlx_core::Error> {
+        let mut tx = conn.begin().await?;
+        (&mut *tx)
+            .execute(crate::query("UPDATE users SET name = ? WHERE id = 1").bind(name))
+            .await?;
+        if commit { tx.commit().await } else { Ok(()) }
+    }
+
+    async fn name_of_user(pool: &D1Pool) -> String {
+        crate::query_scalar("SELECT name FROM users WHERE id = 1")
+            .fetch_one(pool)
+            .await
+            .unwrap()
+    }
+
+    #[test]
+    fn begin_by_shared_pool() {
+        futures_lite::future::block_on(async {
+            let pool = pool().await;
+
+            rename_user(&pool, "alice", true).await.unwrap();
+            assert_eq!(name_of_user(&pool).await, "alice");
+
+            rename_user(&pool, "bob", false).await.unwrap();
+            assert_eq!(name_of_user(&pool).await, "alice");
+
+            /* each transaction has its own connection */
+            let (mut tx1, mut tx2) = (
+                pool.begin_lent().await.unwrap(),
+                pool.begin_lent().await.unwrap(),
+            );
+            (&mut *tx1)
+                .execute("UPDATE users SET name = 'carol' WHERE id = 1")
+                .await
+                .unwrap();
+            (&mut *tx2)
+                .execute("UPDATE users SET name = 'dave' WHERE id = 1")
+                .await
+                .unwrap();
+            tx2.commit().await.unwrap();
+            drop(tx1);
+            assert_eq!(name_of_user(&pool).await, "dave");
+            assert_eq!(pool.lent.lock().unwrap().len(), 4);
+        })
+    }
+
+    #[test]
+    fn begin_by_mutable_pool() {
+        futures_lite::future::block_on(async {
+            let mut pool = pool().await;
+
+            rename_user(&mut pool, "alice", true).await.unwrap();
+            assert_eq!(name_of_user(&pool).await, "alice");
+
+            rename_user(&mut pool, "bob", false).await.unwrap();
+            assert_eq!(name_of_user(&pool).await, "alice");
+            assert!(pool.lent.lock().unwrap().is_empty());
+        })
+    }
+}
@@ -0,0 +1,409 @@
+//! PRAGMAs returning data.
+//!
+//! ref: <https://developers.cloudflare.com/d1/sql-api/sql-statements/#compatible-pragma-statements>
+
+use crate::{D1Connection, row::D1Row};
+use sqlx_core::row::Row as _;
+
+/// Typed PRAGMAs of a [`D1Connection`], created by [`D1Connection::pragma`].
+///
+/// ## Example
+///
+/// ```rust,ignore
+/// let problems = conn.pragma().quick_check().await?;
+/// let violations = conn.pragma().foreign_key_check(None).await?;
+/// if !(problems.is_empty() && violations.is_empty()) {
+///     return worker::Response::error("database is broken", 500);
+/// }
+/// ```
+#[derive(Debug, Clone, Copy)]
+pub struct D1Pragma<'c> {
+    conn: &'c D1Connection,
+}
+
+impl D1Connection {
+    /// Typed PRAGMAs returning data, decoded into structs.
+    pub fn pragma(&self) -> D1Pragma<'_> {
+        D1Pragma { conn: self }
+    }
+}
+
+/// A column of a table, from `PRAGMA table_info`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct D1TableInfo {
+    pub cid: i64,
+    pub name: String,
+    /// declared type, or empty if not declared
+    pub declared_type: String,
+    pub not_null: bool,
+    /// default value as SQL expression
+    pub default_value: Option<String>,
+    /// 1-based position in the primary key, or 0 if not a part of it
+    pub primary_key: i64,
+}
+
+/// A table or view, from `PRAGMA table_list`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct D1TableList {
+    pub schema: String,
+    pub name: String,
+    /// `table`, `view`, `shadow` or `virtual`
+    pub kind: String,
+    /// number of columns
+    pub columns: i64,
+    pub without_rowid: bool,
+    pub strict: bool,
+}
+
+/// An index of a table, from `PRAGMA index_list`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct D1IndexList {
+    pub seq: i64,
+    pub name: String,
+    pub unique: bool,
+    /// `c` for `CREATE INDEX`, `u` for `UNIQUE` constraint, or `pk` for `PRIMARY KEY` constraint
+    pub origin: String,
+    pub partial: bool,
+}
+
+/// A foreign key of a table, from `PRAGMA foreign_key_list`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct D1ForeignKeyList {
+    pub id: i64,
+    /// position of the column in the key
+    pub seq: i64,
+    /// referred table
+    pub table: String,
+    pub from: String,
+    /// referred column, or `None` if refers the primary key implicitly
+    pub to: Option<String>,
+    pub on_update: String,
+    pub on_delete: String,
+    pub r#match: String,
+}
+
+/// A violation of foreign key constraints, from `PRAGMA foreign_key_check`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct D1ForeignKeyViolation {
+    pub table: String,
+    /// `None` for a `WITHOUT ROWID` table
+    pub rowid: Option<i64>,
+    /// referred table
+    pub parent: String,
+    /// `id` of [`D1ForeignKeyList`]
+    pub fkid: i64,
+}
+
+impl D1Pragma<'_> {
+    async fn fetch_all(&self, sql: &str) -> Result<Vec<D1Row>, sqlx_core::Error> {
+        crate::query(sql).fetch_all(self.conn).await
+    }
+
+    /// ref: <https://www.sqlite.org/pragma.html#pragma_table_info>
+    pub async fn table_info(&self, table: &str) -> Result<Vec<D1TableInfo>, sqlx_core::Error> {
+        self.fetch_all(&format!("PRAGMA table_info({})", quote_identifier(table)))
+            .await?
+            .into_iter()
+            .map(|row| {
+                Ok(D1TableInfo {
+                    cid: row.try_get("cid")?,
+                    name: row.try_get("name")?,
+                    declared_type: row.try_get("type")?,
+                    not_null: row.try_get::<i64, _>("notnull")? != 0,
+                    default_value: row.try_get("dflt_value")?,
+                    primary_key: row.try_get("pk")?,
+                })
+            })
+            .collect()
+    }
+
+    /// ref: <https://www.sqlite.org/pragma.html#pragma_table_list>
+    pub async fn table_list(&self) -> Result<Vec<D1TableList>, sqlx_core::Error> {
+        self.fetch_all("PRAGMA table_list")
+            .await?
+            .into_iter()
+            .map(|row| {
+                Ok(D1TableList {
+                    schema: row.try_get("schema")?,
+                    name: row.try_get("name")?,
+                    kind: row.try_get("type")?,
+                    columns: row.try_get("ncol")?,
+                    without_rowid: row.try_get::<i64, _>("wr")? != 0,
+                    strict: row.try_get::<i64, _>("strict")? != 0,
+                })
+            })
+            .collect()
+    }
+
+    /// ref: <https://www.sqlite.org/pragma.html#pragma_index_list>
+    pub async fn index_list(&self, table: &str) -> Result<Vec<D1IndexList>, sqlx_core::Error> {
+        self.fetch_all(&format!("PRAGMA index_list({})", quote_identifier(table)))
+            .await?
+            .into_iter()
+            .map(|row| {
+                Ok(D1IndexList {
+                    seq: row.try_get("seq")?,
+                    name: row.try_get("name")?,
+                    unique: row.try_get::<i64, _>("unique")? != 0,
+                    origin: row.try_get("origin")?,
+                    partial: row.try_get::<i64, _>("partial")? != 0,
+                })
+            })
+            .collect()
+    }
+
+    /// ref: <https://www.sqlite.org/pragma.html#pragma_foreign_key_list>
+    pub async fn foreign_key_list(
+        &self,
+        table: &str,
+    ) -> Result<Vec<D1ForeignKeyList>, sqlx_core::Error> {
+        self.fetch_all(&format!(
+            "PRAGMA foreign_key_list({})",
+            quote_identifier(table)
+        ))
+        .await?
+        .into_iter()
+        .map(|row| {
+            Ok(D1ForeignKeyList {
+                id: row.try_get("id")?,
+                seq: row.try_get("seq")?,
+                table: row.try_get("table")?,
+                from: row.try_get("from")?,
+                to: row.try_get("to")?,
+                on_update: row.try_get("on_update")?,
+                on_delete: row.try_get("on_delete")?,
+                r#match: row.try_get("match")?,
+            })
+        })
+        .collect()
+    }
+
+    /// Check foreign key constraints of the table, or all tables if `None`.
+    ///
+    /// ref: <https://www.sqlite.org/pragma.html#pragma_foreign_key_check>
+    pub async fn foreign_key_check(
+        &self,
+        table: Option<&str>,
+    ) -> Result<Vec<D1ForeignKeyViolation>, sqlx_core::Error> {
+        let sql = match table {
+            Some(table) => format!("PRAGMA foreign_key_check({})", quote_identifier(table)),
+            None => String::from("PRAGMA foreign_key_check"),
+        };
+        self.fetch_all(&sql)
+            .await?
+            .into_iter()
+            .map(|row| {
+                Ok(D1ForeignKeyViolation {
+                    table: row.try_get("table")?,
+                    rowid: row.try_get("rowid")?,
+                    parent: row.try_get("parent")?,
+                    fkid: row.try_get("fkid")?,
+                })
+            })
+            .collect()
+    }
+
+    /// Check the integrity of the database, returning the problems found.
+    /// Empty if no problem.
+    ///
+    /// ref: <https://www.sqlite.org/pragma.html#pragma_quick_check>
+    pub async fn quick_check(&self) -> Result<Vec<String>, sqlx_core::Error> {
+        self.fetch_all("PRAGMA quick_check")
+            .await?
+            .into_iter()
+            .map(|row| row.try_get::<String, _>(0))
+            .filter(|message| !matches!(message.as_deref(), Ok("ok")))
+            .collect()
+    }
+
+    /// ref: <https://www.sqlite.org/pragma.html#pragma_optimize>
+    pub async fn optimize(&self) -> Result<(), sqlx_core::Error> {
+        crate::query("PRAGMA optimize")
+            .execute(self.conn)
+            .await
+            .map(|_| ())
+    }
+}
+
+/// `"identifier"`, as PRAGMAs can't take bound parameters.
+pub(crate) fn quote_identifier(identifier: &str) -> String {
+    format!("\"{}\"", identifier.replace('"', "\"\""))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::quote_identifier;
+
+    #[test]
+    fn quote_identifiers() {
+        assert_eq!(quote_identifier("users"), r#""users""#);
+        assert_eq!(quote_identifier(r#"a"b"#), r#""a""b""#);
+        assert_eq!(quote_identifier("a b); --"), r#""a b); --""#);
+    }
+
+    #[cfg(not(target_arch = "wasm32"))]
+    mod native {
+        use super::super::*;
+        use crate::D1ConnectOptions;
+
+        async fn connect() -> D1Connection {
+            let conn = D1ConnectOptions::sqlite_in_memory()
+                .connect()
+                .await
+                .unwrap();
+            conn.exec_script(
+                r#"CREATE TABLE "a""b" (id INTEGER PRIMARY KEY, "x""y" TEXT NOT NULL DEFAULT 'z');
+                CREATE TABLE users (id INTEGER PRIMARY KEY, email TEXT UNIQUE) STRICT;
+                CREATE TABLE posts (
+                    id INTEGER PRIMARY KEY,
+                    user_id INTEGER REFERENCES users (id) ON DELETE CASCADE
+                );
+                CREATE INDEX posts_by_user ON posts (user_id) WHERE user_id IS NOT NULL;"#,
+            )
+            .await
+            .unwrap();
+            conn
+        }
+
+        #[test]
+        fn table_info_of_quoted_name() {
+            futures_lite::future::block_on(async {
+                let conn = connect().await;
+                assert_eq!(
+                    conn.pragma().table_info(r#"a"b"#).await.unwrap(),
+                    [
+                        D1TableInfo {
+                            cid: 0,
+                            name: "id".into(),
+                            declared_type: "INTEGER".into(),
+                            not_null: false,
+                            default_value: None,
+                            primary_key: 1,
+                        },
+                        D1TableInfo {
+                            cid: 1,
+                            name: r#"x"y"#.into(),
+                            declared_type: "TEXT".into(),
+                            not_null: true,
+                            default_value: Some("'z'".into()),
+                            primary_key: 0,
+                        },
+                    ]
+                );
+                assert!(
+                    conn.pragma()
+                        .table_info("missing")
+                        .await
+                        .unwrap()
+                        .is_empty()
+                );
+            })
+        }
+
+        #[test]
+        fn table_list_and_index_list() {
+            futures_lite::future::block_on(async {
+                let conn = connect().await;
+
+                let users = conn
+                    .pragma()
+                    .table_list()
+                    .await
+                    .unwrap()
+                    .into_iter()
+                    .find(|t| t.schema == "main" && t.name == "users")
+                    .unwrap();
+                assert_eq!(
+                    (
+                        &*users.kind,
+                        users.columns,
+                        users.without_rowid,
+                        users.strict
+                    ),
+                    ("table", 2, false, true)
+                );
+
+                assert_eq!(
+                    conn.pragma().index_list("posts").await.unwrap(),
+                    [D1IndexList {
+                        seq: 0,
+                        name: "posts_by_user".into(),
+                        unique: false,
+                        origin: "c".into(),
+                        partial: true,
+                    }]
+                );
+                assert_eq!(
+                    conn.pragma()
+                        .index_list("users")
+                        .await
+                        .unwrap()
+                        .into_iter()
+                        .map(|i| (i.unique, i.origin))
+                        .collect::<Vec<_>>(),
+                    [(true, "u".into())]
+                );
+            })
+        }
+
+        #[test]
+        fn foreign_keys() {
+            futures_lite::future::block_on(async {
+                let conn = connect().await;
+                assert_eq!(
+                    conn.pragma().foreign_key_list("posts").await.unwrap(),
+                    [D1ForeignKeyList {
+                        id: 0,
+                        seq: 0,
+                        table: "users".into(),
+                        from: "user_id".into(),
+                        to: Some("id".into()),
+                        on_update: "NO ACTION".into(),
+                        on_delete: "CASCADE".into(),
+                        r#match: "NONE".into(),
+                    }]
+                );
+
+                assert!(
+                    conn.pragma()
+                        .foreign_key_check(None)
+                        .await
+                        .unwrap()
+                        .is_empty()
+                );
+                conn.exec_script(
+                    "PRAGMA foreign_keys = OFF;
+                    INSERT INTO posts (id, user_id) VALUES (1, 42);",
+                )
+                .await
+                .unwrap();
+                let violation = D1ForeignKeyViolation {
+                    table: "posts".into(),
+                    rowid: Some(1),
+                    parent: "users".into(),
+                    fkid: 0,
+                };
+                assert_eq!(
+                    conn.pragma().foreign_key_check(None).await.unwrap(),
+                    std::slice::from_ref(&violation)
+                );
+                assert_eq!(
+                    conn.pragma()
+                        .foreign_key_check(Some("posts"))
+                        .await
+                        .unwrap(),
+                    [violation]
+                );
+            })
+        }
+
+        #[test]
+        fn quick_check_and_optimize() {
+            futures_lite::future::block_on(async {
+                let conn = connect().await;
+                assert!(conn.pragma().quick_check().await.unwrap().is_empty());
+                conn.pragma().optimize().await.unwrap();
+            })
+        }
+    }
+}
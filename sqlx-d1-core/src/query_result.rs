@@ -1,9 +1,10 @@
 //! same as <https://github.com/launchbadge/sqlx/blob/d4ae6ffd882ed2de1695c652888d809bc068554e/sqlx-sqlite/src/query_result.rs>
 
-#[derive(Default)]
+#[derive(Debug, Default)]
 pub struct D1QueryResult {
     pub rows_affected: usize,
     pub last_insert_row_id: i64,
+    pub meta: D1QueryMeta,
 }
 
 impl std::iter::Extend<Self> for D1QueryResult {
@@ -11,6 +12,70 @@ impl std::iter::Extend<Self> for D1QueryResult {
         for r in iter {
             self.rows_affected += r.rows_affected;
             self.last_insert_row_id = r.last_insert_row_id;
+            self.meta.accumulate(r.meta);
+        }
+    }
+}
+
+/// Execution metadata D1 reports for each query.
+///
+/// When some results are merged (e.g. by `Executor::execute`), counts and
+/// durations are summed up and the others are taken from the last one.
+///
+/// ref: <https://developers.cloudflare.com/d1/worker-api/return-object/#d1result>
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct D1QueryMeta {
+    /// duration of the operation in the database in milliseconds
+    pub duration: f64,
+    /// number of rows read (scanned) by the query
+    pub rows_read: u64,
+    /// number of rows written by the query
+    pub rows_written: u64,
+    /// size of the database after the query in bytes
+    pub size_after: u64,
+    /// whether the database has been altered
+    pub changed_db: bool,
+    /// region of the database instance that executed the query
+    pub served_by_region: Option<String>,
+    /// whether the query was executed by the primary database instance
+    pub served_by_primary: Option<bool>,
+}
+
+impl D1QueryMeta {
+    pub(crate) fn accumulate(&mut self, other: Self) {
+        self.duration += other.duration;
+        self.rows_read += other.rows_read;
+        self.rows_written += other.rows_written;
+        self.size_after = other.size_after;
+        self.changed_db |= other.changed_db;
+        self.served_by_region = other.served_by_region;
+        self.served_by_primary = other.served_by_primary;
+    }
+}
+
+#[cfg(target_arch = "wasm32")]
+impl D1QueryResult {
+    /// build from `meta` of D1's result object.
+    ///
+    /// ref: <https://developers.cloudflare.com/d1/worker-api/return-object/#d1result>
+    pub(crate) fn from_raw_meta(meta: &worker::wasm_bindgen::JsValue) -> Self {
+        use worker::js_sys::Reflect;
+
+        let get = |key: &str| Reflect::get(meta, &key.into()).unwrap_or_default();
+        let get_number = |key: &str| get(key).as_f64().unwrap_or_default();
+
+        Self {
+            rows_affected: get_number("changes") as usize,
+            last_insert_row_id: get_number("last_row_id") as i64,
+            meta: D1QueryMeta {
+                duration: get_number("duration"),
+                rows_read: get_number("rows_read") as u64,
+                rows_written: get_number("rows_written") as u64,
+                size_after: get_number("size_after") as u64,
+                changed_db: get("changed_db").as_bool().unwrap_or_default(),
+                served_by_region: get("served_by_region").as_string(),
+                served_by_primary: get("served_by_primary").as_bool(),
+            },
         }
     }
 }
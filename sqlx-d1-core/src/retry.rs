@@ -0,0 +1,107 @@
+//! ref: <https://developers.cloudflare.com/d1/best-practices/retry-queries/>
+
+use std::time::Duration;
+
+/// Policy to retry queries failed with transient D1 errors
+/// such as "Network connection lost" or "D1 DB is overloaded".
+///
+/// By default, reads are retried up to 3 attempts in total with exponential
+/// backoff from 100ms to 1s, and writes are not retried because they may be
+/// applied twice.
+///
+/// ## Example
+///
+/// ```rust,ignore
+/// let conn = sqlx_d1::D1ConnectOptions::new(env.d1("DB")?)
+///     .retry_policy(
+///         sqlx_d1::D1RetryPolicy::new()
+///             .max_attempts(5)
+///             .retry_writes(true),
+///     )
+///     .connect()
+///     .await?;
+/// ```
+#[derive(Debug, Clone, PartialEq)]
+pub struct D1RetryPolicy {
+    max_attempts: u32,
+    initial_backoff: Duration,
+    max_backoff: Duration,
+    retry_writes: bool,
+}
+
+impl Default for D1RetryPolicy {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl D1RetryPolicy {
+    pub const fn new() -> Self {
+        Self {
+            max_attempts: 3,
+            initial_backoff: Duration::from_millis(100),
+            max_backoff: Duration::from_secs(1),
+            retry_writes: false,
+        }
+    }
+
+    /// Never retry.
+    pub const fn none() -> Self {
+        Self::new().max_attempts(1)
+    }
+
+    /// Maximum number of attempts including the first one.
+    /// `0` is regarded as `1`.
+    pub const fn max_attempts(mut self, max_attempts: u32) -> Self {
+        self.max_attempts = max_attempts;
+        self
+    }
+
+    /// Backoff before the first retry, doubled for each retry up to `max`.
+    pub const fn backoff(mut self, initial: Duration, max: Duration) -> Self {
+        self.initial_backoff = initial;
+        self.max_backoff = max;
+        self
+    }
+
+    /// Whether to retry also statements that are not idempotent,
+    /// i.e. writes. Default is `false`.
+    pub const fn retry_writes(mut self, yes: bool) -> Self {
+        self.retry_writes = yes;
+        self
+    }
+}
+
+#[cfg(target_arch = "wasm32")]
+impl D1RetryPolicy {
+    /// Run `f` and retry it by this policy while it fails with a transient error.
+    ///
+    /// `idempotent`: whether `f` is safe to be run more than once,
+    /// typically judged by `StatementKind`.
+    pub(crate) async fn run<T, F, Fut>(
+        &self,
+        idempotent: bool,
+        mut f: F,
+    ) -> Result<T, worker::wasm_bindgen::JsValue>
+    where
+        F: FnMut() -> Fut,
+        Fut: Future<Output = Result<T, worker::wasm_bindgen::JsValue>>,
+    {
+        let mut attempts = 1;
+        let mut backoff = self.initial_backoff;
+        loop {
+            match f().await {
+                Err(e)
+                    if attempts < self.max_attempts
+                        && (idempotent || self.retry_writes)
+                        && crate::error::D1Error::from(e.clone()).is_transient() =>
+                {
+                    worker::Delay::from(backoff).await;
+                    backoff = (backoff * 2).min(self.max_backoff);
+                    attempts += 1;
+                }
+                result => return result,
+            }
+        }
+    }
+}
@@ -1,8 +1,10 @@
 use crate::{column::D1Column, value::D1Value};
-use sqlx_core::value::Value;
+use sqlx_core::{HashMap, ext::ustr::UStr, value::Value};
+use std::sync::Arc;
 
 pub struct D1Row {
-    columns: Vec<D1Column>,
+    columns: Arc<[D1Column]>,
+    pub(crate) column_names: Arc<HashMap<UStr, usize>>,
     values: Vec<D1Value>,
 }
 
@@ -25,38 +27,213 @@ impl sqlx_core::row::Row for D1Row {
     }
 }
 
-#[cfg(target_arch = "wasm32")]
+/// For duplicate column names, the first one wins on lookup by name.
+fn column_names_of(columns: &[D1Column]) -> HashMap<UStr, usize> {
+    let mut column_names = HashMap::with_capacity(columns.len());
+    for column in columns {
+        column_names
+            .entry(column.name.clone())
+            .or_insert(column.ordinal);
+    }
+    column_names
+}
+
+#[cfg(not(target_arch = "wasm32"))]
 impl D1Row {
-    pub(crate) fn from_raw(raw: worker::wasm_bindgen::JsValue) -> Result<Self, sqlx_core::Error> {
+    /// build sharing `columns` of the result set, built from the first row with
+    /// the type infos of its values as `RawResultSet` does.
+    pub(crate) fn from_sqlite(
+        row: &sqlx_sqlite::SqliteRow,
+        columns: &mut Option<SharedColumns>,
+    ) -> Result<Self, sqlx_core::Error> {
+        use sqlx_core::{column::Column as _, row::Row as _};
+
+        let values = (0..row.len())
+            .map(|i| D1Value::from_sqlite(row.try_get_raw(i)?))
+            .collect::<Result<Vec<_>, _>>()?;
+        let columns = columns.get_or_insert_with(|| {
+            SharedColumns::from_columns(
+                row.columns()
+                    .iter()
+                    .zip(&values)
+                    .map(|(column, value)| D1Column {
+                        ordinal: column.ordinal(),
+                        name: column.name().to_string().into(),
+                        type_info: value.type_info().into_owned(),
+                    })
+                    .collect(),
+            )
+        });
+
+        Ok(columns.row(values))
+    }
+}
+
+/// Column metadata shared by all rows of a result set.
+#[derive(Clone)]
+pub(crate) struct SharedColumns {
+    columns: Arc<[D1Column]>,
+    column_names: Arc<HashMap<UStr, usize>>,
+}
+
+impl SharedColumns {
+    fn from_columns(columns: Arc<[D1Column]>) -> Self {
+        Self {
+            column_names: Arc::new(column_names_of(&columns)),
+            columns,
+        }
+    }
+
+    fn row(&self, values: Vec<D1Value>) -> D1Row {
+        D1Row {
+            columns: Arc::clone(&self.columns),
+            column_names: Arc::clone(&self.column_names),
+            values,
+        }
+    }
+}
+
+#[cfg(target_arch = "wasm32")]
+impl SharedColumns {
+    /// build with the type infos of the first row's values.
+    fn new(names: &[UStr], first_values: &worker::js_sys::Array) -> Self {
+        Self::from_columns(
+            names
+                .iter()
+                .zip(first_values.iter())
+                .enumerate()
+                .map(|(i, (name, value))| D1Column {
+                    ordinal: i,
+                    name: name.clone(),
+                    type_info: crate::type_info::D1TypeInfo::from_raw(&value),
+                })
+                .collect(),
+        )
+    }
+}
+
+/// Rows of a result set of `raw({ columnNames: true })`.
+#[cfg(target_arch = "wasm32")]
+pub(crate) struct RawResultSet {
+    column_names: Vec<UStr>,
+    /// built from the first row
+    columns: Option<SharedColumns>,
+    raw_rows: worker::js_sys::ArrayIntoIter,
+}
+
+#[cfg(target_arch = "wasm32")]
+impl RawResultSet {
+    pub(crate) fn new(raw: worker::js_sys::Array) -> Self {
+        use worker::js_sys::Array;
+        use worker::wasm_bindgen::JsCast;
+
+        let mut raw_rows = raw.into_iter();
+        let column_names = raw_rows
+            .next()
+            .map(|names| {
+                names
+                    .unchecked_into::<Array>()
+                    .iter()
+                    .map(|name| name.as_string().unwrap_or_default().into())
+                    .collect()
+            })
+            .unwrap_or_default();
+
+        Self {
+            column_names,
+            columns: None,
+            raw_rows,
+        }
+    }
+
+    /// build from row objects of `all()` or `batch()`.
+    ///
+    /// NOTE: this can't keep duplicate column names, and the order of columns
+    /// follows JS object key ordering of the first row.
+    pub(crate) fn from_raw_objects(raw: worker::js_sys::Array) -> Self {
         use worker::js_sys::{Array, Object};
         use worker::wasm_bindgen::JsCast;
 
+        let column_names = raw
+            .iter()
+            .next()
+            .map(|first| {
+                Object::keys(first.unchecked_ref::<Object>())
+                    .iter()
+                    .map(|key| key.as_string().unwrap_or_default().into())
+                    .collect::<Vec<UStr>>()
+            })
+            .unwrap_or_default();
+
+        let raw_rows = raw
+            .iter()
+            .map(|raw_row| {
+                let raw_row = raw_row.unchecked_into::<Object>();
+                column_names
+                    .iter()
+                    .map(|name| {
+                        worker::js_sys::Reflect::get(&raw_row, &(&**name).into())
+                            .unwrap_or_default()
+                    })
+                    .collect::<Array>()
+            })
+            .collect::<Array>();
+
+        Self {
+            column_names,
+            columns: None,
+            raw_rows: raw_rows.into_iter(),
+        }
+    }
+
+    /// number of the remaining rows
+    pub(crate) fn len(&self) -> usize {
+        self.raw_rows.len()
+    }
+
+    pub(crate) fn empty() -> Self {
+        Self {
+            column_names: Vec::new(),
+            columns: None,
+            raw_rows: worker::js_sys::Array::new().into_iter(),
+        }
+    }
+}
+
+#[cfg(target_arch = "wasm32")]
+impl Iterator for RawResultSet {
+    type Item = Result<D1Row, sqlx_core::Error>;
+
+    /// build a row from a row of `raw({ columnNames: true })`, indexed positionally
+    fn next(&mut self) -> Option<Self::Item> {
+        use worker::js_sys::Array;
+        use worker::wasm_bindgen::JsCast;
+
+        let raw = self.raw_rows.next()?.unchecked_into::<Array>();
+
         #[cfg(feature = "DEBUG")]
         {
-            worker::console_debug!("[D1Row::from_raw] raw = `{raw:?}`");
+            worker::console_debug!("[RawResultSet::next] raw = `{raw:?}`");
         }
 
-        let entries = Object::entries(raw.unchecked_ref::<Object>());
-        let len = entries.length() as usize;
-
-        let (mut columns, mut values) = (Vec::with_capacity(len), Vec::with_capacity(len));
-        for (i, pair) in entries.iter().enumerate() {
-            let pair = pair.unchecked_into::<Array>();
-            let (key, value) = (pair.get(0), pair.get(1));
-            columns.push(D1Column {
-                ordinal: i,
-                name: key.as_string().unwrap().into(),
-                type_info: crate::type_info::D1TypeInfo::from_raw(&value),
-            });
-            values.push(value.into());
+        if raw.length() as usize != self.column_names.len() {
+            return Some(Err(sqlx_core::Error::Protocol(format!(
+                "D1 returned a row of {} values for {} columns",
+                raw.length(),
+                self.column_names.len()
+            ))));
         }
 
+        let columns = self
+            .columns
+            .get_or_insert_with(|| SharedColumns::new(&self.column_names, &raw));
+        let values = raw.iter().map(D1Value::from).collect::<Vec<_>>();
+
         #[cfg(feature = "DEBUG")]
         {
-            worker::console_debug!("[D1Row::from_raw] columns = `{columns:?}`");
-            worker::console_debug!("[D1Row::from_raw] values = `{values:?}`");
+            worker::console_debug!("[RawResultSet::next] values = `{values:?}`");
         }
 
-        Ok(Self { columns, values })
+        Some(Ok(columns.row(values)))
     }
 }
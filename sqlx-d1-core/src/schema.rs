@@ -0,0 +1,389 @@
+//! Runtime schema introspection, built from `sqlite_master` and
+//! the PRAGMA table-valued functions.
+
+use crate::{D1Connection, row::D1Row};
+use sqlx_core::row::Row;
+
+/// Snapshot of the database schema, created by [`D1Connection::schema`].
+///
+/// Internal tables of SQLite (`sqlite_*`) and D1 (`_cf_*`) are excluded.
+///
+/// ## Example
+///
+/// ```rust,ignore
+/// let schema = conn.schema().await?;
+/// let users = schema.table("users").expect("`users` table is not deployed");
+/// assert!(users.column("email").is_some_and(|c| !c.nullable));
+/// ```
+#[derive(Debug, Clone, PartialEq)]
+pub struct D1Schema {
+    pub tables: Vec<D1TableSchema>,
+    pub views: Vec<D1ViewSchema>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct D1TableSchema {
+    pub name: String,
+    pub columns: Vec<D1ColumnSchema>,
+    /// columns of the primary key in the key order, or empty if not declared
+    pub primary_key: Vec<String>,
+    pub indexes: Vec<D1IndexSchema>,
+    pub foreign_keys: Vec<D1ForeignKeySchema>,
+    /// the `CREATE TABLE` statement
+    pub sql: Option<String>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct D1ViewSchema {
+    pub name: String,
+    pub columns: Vec<D1ColumnSchema>,
+    /// the `CREATE VIEW` statement
+    pub sql: Option<String>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct D1ColumnSchema {
+    pub name: String,
+    /// declared type, or empty if not declared
+    pub declared_type: String,
+    pub nullable: bool,
+    /// default value as SQL expression
+    pub default_value: Option<String>,
+    pub primary_key: bool,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct D1IndexSchema {
+    pub name: String,
+    /// indexed columns in the index order, `None` for an expression
+    pub columns: Vec<Option<String>>,
+    pub unique: bool,
+    /// `c` for `CREATE INDEX`, `u` for `UNIQUE` constraint, or `pk` for `PRIMARY KEY` constraint
+    pub origin: String,
+    pub partial: bool,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct D1ForeignKeySchema {
+    pub columns: Vec<String>,
+    /// referred table
+    pub parent: String,
+    /// referred columns, `None` for ones referring the primary key implicitly
+    pub parent_columns: Vec<Option<String>>,
+    pub on_update: String,
+    pub on_delete: String,
+}
+
+impl D1Schema {
+    pub fn table(&self, name: &str) -> Option<&D1TableSchema> {
+        self.tables.iter().find(|t| t.name == name)
+    }
+
+    pub fn view(&self, name: &str) -> Option<&D1ViewSchema> {
+        self.views.iter().find(|v| v.name == name)
+    }
+}
+
+impl D1TableSchema {
+    pub fn column(&self, name: &str) -> Option<&D1ColumnSchema> {
+        self.columns.iter().find(|c| c.name == name)
+    }
+
+    pub fn index(&self, name: &str) -> Option<&D1IndexSchema> {
+        self.indexes.iter().find(|i| i.name == name)
+    }
+}
+
+impl D1ViewSchema {
+    pub fn column(&self, name: &str) -> Option<&D1ColumnSchema> {
+        self.columns.iter().find(|c| c.name == name)
+    }
+}
+
+const OBJECTS: &str = "SELECT m.type, m.name, m.sql \
+    FROM sqlite_master AS m \
+    WHERE m.type IN ('table', 'view') \
+    AND m.name NOT LIKE 'sqlite\\_%' ESCAPE '\\' AND m.name NOT LIKE '\\_cf\\_%' ESCAPE '\\' \
+    ORDER BY m.name";
+const COLUMNS: &str = "SELECT m.name AS object_name, c.name, c.type, c.\"notnull\", c.dflt_value, c.pk \
+    FROM sqlite_master AS m JOIN pragma_table_info(m.name) AS c \
+    WHERE m.type IN ('table', 'view') \
+    AND m.name NOT LIKE 'sqlite\\_%' ESCAPE '\\' AND m.name NOT LIKE '\\_cf\\_%' ESCAPE '\\' \
+    ORDER BY m.name, c.cid";
+const INDEXES: &str = "SELECT m.name AS object_name, i.name, i.\"unique\", i.origin, i.partial, c.name AS column_name \
+    FROM sqlite_master AS m JOIN pragma_index_list(m.name) AS i JOIN pragma_index_info(i.name) AS c \
+    WHERE m.type = 'table' \
+    AND m.name NOT LIKE 'sqlite\\_%' ESCAPE '\\' AND m.name NOT LIKE '\\_cf\\_%' ESCAPE '\\' \
+    ORDER BY m.name, i.seq, c.seqno";
+const FOREIGN_KEYS: &str = "SELECT m.name AS object_name, f.id, f.\"table\", f.\"from\", f.\"to\", f.on_update, f.on_delete \
+    FROM sqlite_master AS m JOIN pragma_foreign_key_list(m.name) AS f \
+    WHERE m.type = 'table' \
+    AND m.name NOT LIKE 'sqlite\\_%' ESCAPE '\\' AND m.name NOT LIKE '\\_cf\\_%' ESCAPE '\\' \
+    ORDER BY m.name, f.id, f.seq";
+
+impl D1Connection {
+    /// Take a snapshot of the current schema.
+    pub async fn schema(&self) -> Result<D1Schema, sqlx_core::Error> {
+        self.ensure_open()?;
+        let fetch_all = |sql: &'static str| crate::query(sql).fetch_all(self);
+        D1Schema::from_rows(
+            fetch_all(OBJECTS).await?,
+            fetch_all(COLUMNS).await?,
+            fetch_all(INDEXES).await?,
+            fetch_all(FOREIGN_KEYS).await?,
+        )
+    }
+}
+
+fn int(row: &D1Row, column: &str) -> Result<i64, sqlx_core::Error> {
+    row.try_get(column)
+}
+fn text(row: &D1Row, column: &str) -> Result<String, sqlx_core::Error> {
+    row.try_get(column)
+}
+fn optional_text(row: &D1Row, column: &str) -> Result<Option<String>, sqlx_core::Error> {
+    row.try_get(column)
+}
+
+impl D1Schema {
+    fn from_rows(
+        objects: Vec<D1Row>,
+        columns: Vec<D1Row>,
+        indexes: Vec<D1Row>,
+        foreign_keys: Vec<D1Row>,
+    ) -> Result<Self, sqlx_core::Error> {
+        let mut schema = D1Schema {
+            tables: Vec::new(),
+            views: Vec::new(),
+        };
+
+        for row in objects {
+            let (name, sql) = (text(&row, "name")?, optional_text(&row, "sql")?);
+            if text(&row, "type")? == "table" {
+                schema.tables.push(D1TableSchema {
+                    name,
+                    columns: Vec::new(),
+                    primary_key: Vec::new(),
+                    indexes: Vec::new(),
+                    foreign_keys: Vec::new(),
+                    sql,
+                });
+            } else {
+                schema.views.push(D1ViewSchema {
+                    name,
+                    columns: Vec::new(),
+                    sql,
+                });
+            }
+        }
+
+        let mut primary_keys = Vec::new();
+        for row in columns {
+            let object_name = text(&row, "object_name")?;
+            let primary_key_position = int(&row, "pk")?;
+            let column = D1ColumnSchema {
+                name: text(&row, "name")?,
+                declared_type: text(&row, "type")?,
+                nullable: int(&row, "notnull")? == 0,
+                default_value: optional_text(&row, "dflt_value")?,
+                primary_key: primary_key_position > 0,
+            };
+            if column.primary_key {
+                primary_keys.push((
+                    object_name.clone(),
+                    primary_key_position,
+                    column.name.clone(),
+                ));
+            }
+            if let Some(table) = schema.tables.iter_mut().find(|t| t.name == object_name) {
+                table.columns.push(column);
+            } else if let Some(view) = schema.views.iter_mut().find(|v| v.name == object_name) {
+                view.columns.push(column);
+            }
+        }
+        primary_keys.sort();
+        for (table_name, _, column_name) in primary_keys {
+            if let Some(table) = schema.table_mut(&table_name) {
+                table.primary_key.push(column_name);
+            }
+        }
+
+        for row in indexes {
+            let Some(table) = schema.table_mut(&text(&row, "object_name")?) else {
+                continue;
+            };
+            let (name, column) = (text(&row, "name")?, optional_text(&row, "column_name")?);
+            match table.indexes.last_mut() {
+                Some(index) if index.name == name => index.columns.push(column),
+                _ => table.indexes.push(D1IndexSchema {
+                    name,
+                    columns: vec![column],
+                    unique: int(&row, "unique")? != 0,
+                    origin: text(&row, "origin")?,
+                    partial: int(&row, "partial")? != 0,
+                }),
+            }
+        }
+
+        let mut last_foreign_key_id = None;
+        for row in foreign_keys {
+            let table_name = text(&row, "object_name")?;
+            let Some(table) = schema.table_mut(&table_name) else {
+                continue;
+            };
+            let id = (table_name, int(&row, "id")?);
+            let (column, parent_column) = (text(&row, "from")?, optional_text(&row, "to")?);
+            match table.foreign_keys.last_mut() {
+                Some(foreign_key) if last_foreign_key_id.as_ref() == Some(&id) => {
+                    foreign_key.columns.push(column);
+                    foreign_key.parent_columns.push(parent_column);
+                }
+                _ => table.foreign_keys.push(D1ForeignKeySchema {
+                    columns: vec![column],
+                    parent: text(&row, "table")?,
+                    parent_columns: vec![parent_column],
+                    on_update: text(&row, "on_update")?,
+                    on_delete: text(&row, "on_delete")?,
+                }),
+            }
+            last_foreign_key_id = Some(id);
+        }
+
+        Ok(schema)
+    }
+
+    fn table_mut(&mut self, name: &str) -> Option<&mut D1TableSchema> {
+        self.tables.iter_mut().find(|t| t.name == name)
+    }
+}
+
+#[cfg(all(test, not(target_arch = "wasm32")))]
+mod tests {
+    use super::*;
+    use crate::D1ConnectOptions;
+
+    #[test]
+    fn schema_of_tables_and_views() {
+        futures_lite::future::block_on(async {
+            let conn = D1ConnectOptions::sqlite_in_memory()
+                .connect()
+                .await
+                .unwrap();
+            conn.exec_script(
+                "CREATE TABLE users (
+                    id INTEGER PRIMARY KEY,
+                    email TEXT NOT NULL UNIQUE,
+                    name TEXT DEFAULT 'anonymous'
+                );
+                CREATE TABLE memberships (
+                    user_id INTEGER NOT NULL REFERENCES users (id) ON DELETE CASCADE,
+                    group_name TEXT NOT NULL,
+                    PRIMARY KEY (group_name, user_id)
+                );
+                CREATE INDEX memberships_by_user ON memberships (user_id);
+                CREATE VIEW user_names AS SELECT id, name FROM users;
+                CREATE TABLE _cf_KV (key TEXT PRIMARY KEY, value BLOB);
+                ANALYZE;",
+            )
+            .await
+            .unwrap();
+
+            let schema = conn.schema().await.unwrap();
+
+            assert_eq!(
+                schema.tables.iter().map(|t| &*t.name).collect::<Vec<_>>(),
+                ["memberships", "users"],
+                "`_cf_*` and `sqlite_*` (here `sqlite_stat1` by `ANALYZE`) are excluded"
+            );
+
+            let users = schema.table("users").unwrap();
+            assert_eq!(
+                users.columns,
+                [
+                    D1ColumnSchema {
+                        name: "id".into(),
+                        declared_type: "INTEGER".into(),
+                        nullable: true,
+                        default_value: None,
+                        primary_key: true,
+                    },
+                    D1ColumnSchema {
+                        name: "email".into(),
+                        declared_type: "TEXT".into(),
+                        nullable: false,
+                        default_value: None,
+                        primary_key: false,
+                    },
+                    D1ColumnSchema {
+                        name: "name".into(),
+                        declared_type: "TEXT".into(),
+                        nullable: true,
+                        default_value: Some("'anonymous'".into()),
+                        primary_key: false,
+                    },
+                ]
+            );
+            assert_eq!(users.primary_key, ["id"]);
+            assert_eq!(
+                users.indexes,
+                [D1IndexSchema {
+                    name: "sqlite_autoindex_users_1".into(),
+                    columns: vec![Some("email".into())],
+                    unique: true,
+                    origin: "u".into(),
+                    partial: false,
+                }]
+            );
+            assert!(users.foreign_keys.is_empty());
+
+            let memberships = schema.table("memberships").unwrap();
+            assert_eq!(memberships.primary_key, ["group_name", "user_id"]);
+            assert_eq!(
+                memberships.index("memberships_by_user"),
+                Some(&D1IndexSchema {
+                    name: "memberships_by_user".into(),
+                    columns: vec![Some("user_id".into())],
+                    unique: false,
+                    origin: "c".into(),
+                    partial: false,
+                })
+            );
+            assert_eq!(
+                memberships
+                    .index("sqlite_autoindex_memberships_1")
+                    .map(|i| (&*i.origin, &*i.columns)),
+                Some((
+                    "pk",
+                    &[Some("group_name".into()), Some("user_id".into())][..]
+                ))
+            );
+            assert_eq!(
+                memberships.foreign_keys,
+                [D1ForeignKeySchema {
+                    columns: vec!["user_id".into()],
+                    parent: "users".into(),
+                    parent_columns: vec![Some("id".into())],
+                    on_update: "NO ACTION".into(),
+                    on_delete: "CASCADE".into(),
+                }]
+            );
+
+            let user_names = schema.view("user_names").unwrap();
+            assert_eq!(
+                user_names
+                    .columns
+                    .iter()
+                    .map(|c| &*c.name)
+                    .collect::<Vec<_>>(),
+                ["id", "name"]
+            );
+            assert!(
+                user_names
+                    .sql
+                    .as_deref()
+                    .unwrap()
+                    .starts_with("CREATE VIEW")
+            );
+        })
+    }
+}
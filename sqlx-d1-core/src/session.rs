@@ -0,0 +1,232 @@
+//! ref: <https://developers.cloudflare.com/d1/best-practices/read-replication/>
+
+use crate::{
+    D1, D1Connection, arguments::D1Arguments, batch::D1BatchResult, query_result::D1QueryResult,
+    row::D1Row,
+};
+use sqlx_core::{Either, query::Query};
+
+#[cfg(target_arch = "wasm32")]
+use crate::{error::D1Error, ffi};
+
+/// Where a [`D1Session`] starts from.
+///
+/// ref: <https://developers.cloudflare.com/d1/worker-api/d1-database/#withsession>
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum D1SessionConstraint {
+    /// The first query is directed to the primary database instance,
+    /// so that it sees the latest data.
+    FirstPrimary,
+    /// The first query is directed to any database instance,
+    /// so that it has the lowest latency.
+    FirstUnconstrained,
+    /// The first query is directed to a database instance that is
+    /// at least as up-to-date as the bookmark, for example one
+    /// received from a client via HTTP header.
+    Bookmark(String),
+}
+
+impl D1SessionConstraint {
+    #[cfg(target_arch = "wasm32")]
+    fn as_str(&self) -> &str {
+        match self {
+            Self::FirstPrimary => "first-primary",
+            Self::FirstUnconstrained => "first-unconstrained",
+            Self::Bookmark(bookmark) => bookmark,
+        }
+    }
+}
+
+/// A D1 session for read replication, providing sequential consistency
+/// for queries executed on it.
+///
+/// `&D1Session` implements `Executor` just like `&D1Connection` does.
+///
+/// ## Example
+///
+/// ```rust,ignore
+/// let session = conn.with_session(
+///     match req.headers().get("x-d1-bookmark")? {
+///         Some(bookmark) => D1SessionConstraint::Bookmark(bookmark),
+///         None => D1SessionConstraint::FirstUnconstrained,
+///     },
+/// )?;
+///
+/// let users = sqlx_d1::query_as!(User, "SELECT * FROM users")
+///     .fetch_all(&session)
+///     .await?;
+///
+/// let mut res = worker::Response::from_json(&users)?;
+/// if let Some(bookmark) = session.latest_bookmark() {
+///     res.headers_mut().set("x-d1-bookmark", &bookmark)?;
+/// }
+/// ```
+pub struct D1Session {
+    #[cfg(target_arch = "wasm32")]
+    inner: ffi::D1DatabaseSession,
+    #[cfg(target_arch = "wasm32")]
+    settings: crate::connection::QuerySettings,
+
+    /// a clone of the connection, as SQLite standing in for D1 has no replicas
+    #[cfg(not(target_arch = "wasm32"))]
+    inner: D1Connection,
+}
+
+const _: () = {
+    /* SAFETY: used in single-threaded Workers */
+    unsafe impl Send for D1Session {}
+    unsafe impl Sync for D1Session {}
+
+    impl D1Connection {
+        /// Start a D1 session from the given constraint or bookmark.
+        pub fn with_session(
+            &self,
+            #[allow(unused)] constraint: D1SessionConstraint,
+        ) -> Result<D1Session, sqlx_core::Error> {
+            self.ensure_open()?;
+            #[cfg(not(target_arch = "wasm32"))]
+            {
+                Ok(D1Session {
+                    inner: self.clone(),
+                })
+            }
+            #[cfg(target_arch = "wasm32")]
+            {
+                Ok(D1Session {
+                    inner: ffi::with_session(&self.inner, constraint.as_str())
+                        .map_err(D1Error::from)?,
+                    settings: self.settings.clone(),
+                })
+            }
+        }
+    }
+
+    impl D1Session {
+        /// The bookmark of the latest query executed in this session,
+        /// or `None` if no query has been executed yet.
+        ///
+        /// Always `None` on native targets, where there are no replicas.
+        pub fn latest_bookmark(&self) -> Option<String> {
+            #[cfg(not(target_arch = "wasm32"))]
+            {
+                None
+            }
+            #[cfg(target_arch = "wasm32")]
+            {
+                self.inner.get_bookmark()
+            }
+        }
+
+        /// Execute the queries as a batch in this session.
+        ///
+        /// See [`D1Connection::batch`] for details.
+        pub async fn batch<'q>(
+            &self,
+            #[allow(unused)] queries: impl IntoIterator<Item = Query<'q, D1, D1Arguments>>,
+        ) -> Result<Vec<D1BatchResult>, sqlx_core::Error> {
+            #[cfg(not(target_arch = "wasm32"))]
+            {
+                self.inner.batch(queries).await
+            }
+            #[cfg(target_arch = "wasm32")]
+            {
+                crate::batch::batch_on(&self.inner, &self.settings.retry_policy, queries).await
+            }
+        }
+    }
+
+    impl std::fmt::Debug for D1Session {
+        fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+            f.debug_struct("D1Session").finish()
+        }
+    }
+
+    impl<'c> sqlx_core::executor::Executor<'c> for &'c D1Session {
+        type Database = crate::D1;
+
+        fn fetch_many<'e, 'q: 'e, E>(
+            self,
+            #[allow(unused)] query: E,
+        ) -> futures_core::stream::BoxStream<
+            'e,
+            Result<Either<D1QueryResult, D1Row>, sqlx_core::Error>,
+        >
+        where
+            'c: 'e,
+            E: 'q + sqlx_core::executor::Execute<'q, Self::Database>,
+        {
+            #[cfg(not(target_arch = "wasm32"))]
+            {
+                <&'c D1Connection as sqlx_core::executor::Executor<'c>>::fetch_many(
+                    &self.inner,
+                    query,
+                )
+            }
+            #[cfg(target_arch = "wasm32")]
+            {
+                crate::connection::fetch_many_on(&self.inner, &self.settings, query)
+            }
+        }
+
+        fn fetch_optional<'e, 'q: 'e, E>(
+            self,
+            #[allow(unused)] query: E,
+        ) -> crate::ResultFuture<'e, Option<D1Row>>
+        where
+            'c: 'e,
+            E: 'q + sqlx_core::executor::Execute<'q, Self::Database>,
+        {
+            #[cfg(not(target_arch = "wasm32"))]
+            {
+                <&'c D1Connection as sqlx_core::executor::Executor<'c>>::fetch_optional(
+                    &self.inner,
+                    query,
+                )
+            }
+            #[cfg(target_arch = "wasm32")]
+            {
+                crate::connection::fetch_optional_on(&self.inner, &self.settings, query)
+            }
+        }
+
+        fn prepare_with<'e, 'q: 'e>(
+            self,
+            sql: &'q str,
+            #[allow(unused)] parameters: &'e [<Self::Database as sqlx_core::database::Database>::TypeInfo],
+        ) -> crate::ResultFuture<'e, <Self::Database as sqlx_core::database::Database>::Statement<'q>>
+        where
+            'c: 'e,
+        {
+            #[cfg(not(target_arch = "wasm32"))]
+            {
+                <&'c D1Connection as sqlx_core::executor::Executor<'c>>::prepare_with(
+                    &self.inner,
+                    sql,
+                    parameters,
+                )
+            }
+            #[cfg(target_arch = "wasm32")]
+            {
+                let statement = crate::statement::D1Statement::prepare_on(&self.inner, sql);
+                Box::pin(async { statement })
+            }
+        }
+
+        fn describe<'e, 'q: 'e>(
+            self,
+            #[allow(unused)] sql: &'q str,
+        ) -> crate::ResultFuture<'e, sqlx_core::describe::Describe<Self::Database>>
+        where
+            'c: 'e,
+        {
+            #[cfg(not(target_arch = "wasm32"))]
+            {
+                <&'c D1Connection as sqlx_core::executor::Executor<'c>>::describe(&self.inner, sql)
+            }
+            #[cfg(target_arch = "wasm32")]
+            {
+                unreachable!("wasm32 describe")
+            }
+        }
+    }
+};
@@ -0,0 +1,272 @@
+//! Per-query spans of the `tracing` feature.
+//!
+//! Without the feature, these are just no-op.
+
+use crate::{exec::D1ExecResult, query_result::D1QueryResult};
+
+/// Run the future of executing `sql` in a span for it.
+pub(crate) fn instrument<F: Future>(
+    #[allow(unused)] sql: &str,
+    future: F,
+) -> impl Future<Output = F::Output> {
+    #[cfg(feature = "tracing")]
+    {
+        use tracing::field::Empty;
+
+        tracing::Instrument::instrument(
+            future,
+            tracing::info_span!(
+                "d1.query",
+                db.system = "cloudflare_d1",
+                db.statement = sql,
+                db.statement_kind = ?crate::statement::StatementKind::of(sql),
+                db.rows_returned = Empty,
+                db.rows_affected = Empty,
+                d1.rows_read = Empty,
+                d1.rows_written = Empty,
+                d1.duration_ms = Empty,
+                error = Empty,
+            ),
+        )
+    }
+    #[cfg(not(feature = "tracing"))]
+    {
+        future
+    }
+}
+
+/// Run the future of executing `statements` as a batch in a span for them.
+pub(crate) fn instrument_batch<F: Future>(
+    #[allow(unused)] statements: &[&str],
+    future: F,
+) -> impl Future<Output = F::Output> {
+    #[cfg(feature = "tracing")]
+    {
+        use tracing::field::Empty;
+
+        tracing::Instrument::instrument(
+            future,
+            tracing::info_span!(
+                "d1.batch",
+                db.system = "cloudflare_d1",
+                db.statement = statements.join(";\n"),
+                d1.batch_size = statements.len(),
+                db.rows_returned = Empty,
+                db.rows_affected = Empty,
+                d1.rows_read = Empty,
+                d1.rows_written = Empty,
+                d1.duration_ms = Empty,
+                error = Empty,
+            ),
+        )
+    }
+    #[cfg(not(feature = "tracing"))]
+    {
+        future
+    }
+}
+
+/// Run the future of executing the script `sql` in a span for it.
+pub(crate) fn instrument_script<F: Future>(
+    #[allow(unused)] sql: &str,
+    future: F,
+) -> impl Future<Output = F::Output> {
+    #[cfg(feature = "tracing")]
+    {
+        use tracing::field::Empty;
+
+        tracing::Instrument::instrument(
+            future,
+            tracing::info_span!(
+                "d1.exec",
+                db.system = "cloudflare_d1",
+                db.statement = sql,
+                d1.statements = Empty,
+                d1.duration_ms = Empty,
+                error = Empty,
+            ),
+        )
+    }
+    #[cfg(not(feature = "tracing"))]
+    {
+        future
+    }
+}
+
+/// Record the result to the current span created by [`instrument`] or [`instrument_batch`].
+/// The results of a batch are summed up into `query_result`.
+pub(crate) fn record(
+    #[allow(unused)] rows_returned: usize,
+    #[allow(unused)] query_result: &D1QueryResult,
+) {
+    #[cfg(feature = "tracing")]
+    {
+        let span = tracing::Span::current();
+        span.record("db.rows_returned", rows_returned);
+        span.record("db.rows_affected", query_result.rows_affected);
+        span.record("d1.rows_read", query_result.meta.rows_read);
+        span.record("d1.rows_written", query_result.meta.rows_written);
+        span.record("d1.duration_ms", query_result.meta.duration);
+    }
+}
+
+/// Record the result to the current span created by [`instrument_script`].
+pub(crate) fn record_script(#[allow(unused)] exec_result: &D1ExecResult) {
+    #[cfg(feature = "tracing")]
+    {
+        let span = tracing::Span::current();
+        span.record("d1.statements", exec_result.count);
+        span.record("d1.duration_ms", exec_result.duration);
+    }
+}
+
+/// Record the error to the current span created by any of the `instrument*`.
+pub(crate) fn record_error(#[allow(unused)] error: &impl std::fmt::Display) {
+    #[cfg(feature = "tracing")]
+    {
+        tracing::Span::current().record("error", tracing::field::display(error));
+    }
+}
+
+#[cfg(all(test, feature = "tracing", not(target_arch = "wasm32")))]
+mod tests {
+    use crate::{D1ConnectOptions, D1Connection};
+    use sqlx_core::executor::Executor;
+    use std::sync::{Arc, Mutex};
+    use tracing::{
+        Event, Metadata, Subscriber,
+        field::{Field, Visit},
+        span,
+    };
+
+    /// Metadata and the recorded fields of a span.
+    type Recorded = (&'static Metadata<'static>, Vec<(String, String)>);
+
+    /// Collects the name and the recorded fields of each span.
+    #[derive(Clone, Default)]
+    struct Spans {
+        spans: Arc<Mutex<Vec<Recorded>>>,
+        entered: Arc<Mutex<Vec<span::Id>>>,
+    }
+
+    struct Recorder<'s> {
+        spans: &'s Spans,
+        index: usize,
+    }
+
+    impl Visit for Recorder<'_> {
+        fn record_debug(&mut self, field: &Field, value: &dyn std::fmt::Debug) {
+            self.record_str(field, &format!("{value:?}"));
+        }
+        fn record_str(&mut self, field: &Field, value: &str) {
+            self.spans.spans.lock().unwrap()[self.index]
+                .1
+                .push((field.name().to_owned(), value.to_owned()));
+        }
+    }
+
+    impl Subscriber for Spans {
+        fn enabled(&self, _: &Metadata<'_>) -> bool {
+            true
+        }
+        fn new_span(&self, attributes: &span::Attributes<'_>) -> span::Id {
+            let index = {
+                let mut spans = self.spans.lock().unwrap();
+                spans.push((attributes.metadata(), Vec::new()));
+                spans.len() - 1
+            };
+            attributes.record(&mut Recorder { spans: self, index });
+            span::Id::from_u64(index as u64 + 1)
+        }
+        fn record(&self, id: &span::Id, values: &span::Record<'_>) {
+            values.record(&mut Recorder {
+                spans: self,
+                index: id.into_u64() as usize - 1,
+            });
+        }
+        fn record_follows_from(&self, _: &span::Id, _: &span::Id) {}
+        fn event(&self, _: &Event<'_>) {}
+        fn enter(&self, id: &span::Id) {
+            self.entered.lock().unwrap().push(id.clone());
+        }
+        fn exit(&self, _: &span::Id) {
+            self.entered.lock().unwrap().pop();
+        }
+        fn current_span(&self) -> tracing_core::span::Current {
+            match self.entered.lock().unwrap().last() {
+                Some(id) => tracing_core::span::Current::new(
+                    id.clone(),
+                    self.spans.lock().unwrap()[id.into_u64() as usize - 1].0,
+                ),
+                None => tracing_core::span::Current::none(),
+            }
+        }
+    }
+
+    impl Spans {
+        fn field(&self, name: &str, field: &str) -> Option<String> {
+            let spans = self.spans.lock().unwrap();
+            let (_, fields) = spans.iter().rev().find(|(m, _)| m.name() == name)?;
+            fields
+                .iter()
+                .rev()
+                .find(|(f, _)| f == field)
+                .map(|(_, value)| value.clone())
+        }
+    }
+
+    async fn connect() -> D1Connection {
+        D1ConnectOptions::sqlite_in_memory()
+            .connect()
+            .await
+            .unwrap()
+    }
+
+    #[test]
+    fn spans_of_query_batch_and_script() {
+        let spans = Spans::default();
+        tracing::subscriber::with_default(spans.clone(), || {
+            futures_lite::future::block_on(async {
+                let conn = connect().await;
+
+                conn.exec_script(
+                    "CREATE TABLE users (id INTEGER PRIMARY KEY, name TEXT NOT NULL);",
+                )
+                .await
+                .unwrap();
+                assert_eq!(
+                    spans.field("d1.exec", "d1.statements").as_deref(),
+                    Some("1")
+                );
+
+                conn.batch([
+                    sqlx_core::query::query("INSERT INTO users (name) VALUES (?)").bind("a"),
+                    sqlx_core::query::query("INSERT INTO users (name) VALUES (?)").bind("b"),
+                ])
+                .await
+                .unwrap();
+                assert_eq!(
+                    spans.field("d1.batch", "d1.batch_size").as_deref(),
+                    Some("2")
+                );
+                assert_eq!(
+                    spans.field("d1.batch", "db.rows_affected").as_deref(),
+                    Some("2")
+                );
+
+                (&conn).fetch_all("SELECT name FROM users").await.unwrap();
+                assert_eq!(
+                    spans.field("d1.query", "db.statement").as_deref(),
+                    Some("SELECT name FROM users")
+                );
+                assert_eq!(
+                    spans.field("d1.query", "db.rows_returned").as_deref(),
+                    Some("2")
+                );
+
+                assert!((&conn).execute("SELECT * FROM missing").await.is_err());
+                assert!(spans.field("d1.query", "error").is_some());
+            })
+        });
+    }
+}
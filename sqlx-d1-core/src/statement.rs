@@ -1,8 +1,137 @@
-use sqlx_core::impl_statement_query;
-use std::borrow::Cow;
+use crate::{D1, arguments::D1Arguments, column::D1Column, type_info::D1TypeInfo};
+use sqlx_core::{
+    Either, arguments::Arguments as _, error::BoxDynError, executor::Execute, impl_statement_query,
+    query::Query, query_scalar::QueryScalar,
+};
+use std::{borrow::Cow, sync::Arc};
 
 pub struct D1Statement<'q> {
     pub(crate) sql: Cow<'q, str>,
+    /// number of parameters, if known from describe data
+    pub(crate) parameters: Option<usize>,
+    pub(crate) columns: Arc<[D1Column]>,
+    /// reused for every execution of queries made from this statement,
+    /// or `None` if this is not yet prepared on a D1 binding
+    #[cfg(target_arch = "wasm32")]
+    pub(crate) prepared: Option<worker_sys::D1PreparedStatement>,
+}
+
+#[cfg(target_arch = "wasm32")]
+const _: () = {
+    /* SAFETY: used in single-threaded Workers */
+    unsafe impl Send for D1Statement<'_> {}
+    unsafe impl Sync for D1Statement<'_> {}
+};
+
+impl<'q> D1Statement<'q> {
+    pub(crate) fn new(sql: impl Into<Cow<'q, str>>) -> Self {
+        Self {
+            sql: sql.into(),
+            parameters: None,
+            columns: Arc::new([]),
+            #[cfg(target_arch = "wasm32")]
+            prepared: None,
+        }
+    }
+
+    #[cfg(target_arch = "wasm32")]
+    pub(crate) fn prepare_on(
+        d1: &impl crate::ffi::D1Binding,
+        sql: &'q str,
+    ) -> Result<Self, sqlx_core::Error> {
+        Ok(Self {
+            prepared: Some(d1.prepare(sql).map_err(crate::error::D1Error::from)?),
+            ..Self::new(sql)
+        })
+    }
+
+    #[cfg(not(target_arch = "wasm32"))]
+    pub(crate) fn from_describe(sql: &'q str, describe: sqlx_core::describe::Describe<D1>) -> Self {
+        Self {
+            parameters: describe.parameters().map(|ps| match ps {
+                Either::Left(type_infos) => type_infos.len(),
+                Either::Right(n) => n,
+            }),
+            columns: describe.columns.into(),
+            ..Self::new(sql)
+        }
+    }
+
+    /// Check the number of arguments against the parameters if they're known.
+    pub(crate) fn check_arguments(
+        &self,
+        arguments: Option<&D1Arguments>,
+    ) -> Result<(), sqlx_core::Error> {
+        let n_arguments = arguments.map_or(0, |a| a.len());
+        match self.parameters {
+            Some(n_parameters) if n_parameters != n_arguments => {
+                Err(sqlx_core::Error::InvalidArgument(format!(
+                    "expected {n_parameters} parameters, got {n_arguments}: `{}`",
+                    self.sql
+                )))
+            }
+            _ => Ok(()),
+        }
+    }
+}
+
+/// `take_arguments`, checking them against the query's statement if any.
+pub(crate) fn take_checked_arguments<'q>(
+    query: &mut impl Execute<'q, D1>,
+) -> Result<Option<D1Arguments>, sqlx_core::Error> {
+    let arguments = query.take_arguments().map_err(sqlx_core::Error::Encode)?;
+    if let Some(statement) = query.statement() {
+        statement.check_arguments(arguments.as_ref())?;
+    }
+    Ok(arguments)
+}
+
+/// used by the `query!` family to embed describe data at compile time.
+#[doc(hidden)]
+impl D1Statement<'static> {
+    /// `columns`: pairs of name and type name serialized by the `offline` feature
+    pub fn __from_describe(
+        sql: &'static str,
+        parameters: Option<usize>,
+        columns: &[(&'static str, &'static str)],
+    ) -> Self {
+        Self {
+            parameters,
+            columns: columns
+                .iter()
+                .enumerate()
+                .map(|(i, (name, type_name))| D1Column {
+                    ordinal: i,
+                    name: sqlx_core::ext::ustr::UStr::Static(name),
+                    type_info: D1TypeInfo::from_name(type_name),
+                })
+                .collect(),
+            ..Self::new(sql)
+        }
+    }
+
+    pub fn __query_with_result(
+        &'static self,
+        arguments: Result<D1Arguments, BoxDynError>,
+    ) -> Query<'static, D1, D1Arguments> {
+        match arguments {
+            Ok(arguments) => sqlx_core::query::query_statement_with(self, arguments),
+            Err(e) => sqlx_core::query::query_with_result(&self.sql, Err(e)),
+        }
+    }
+
+    pub fn __query_scalar_with_result<O>(
+        &'static self,
+        arguments: Result<D1Arguments, BoxDynError>,
+    ) -> QueryScalar<'static, D1, O, D1Arguments>
+    where
+        (O,): for<'r> sqlx_core::from_row::FromRow<'r, crate::row::D1Row>,
+    {
+        match arguments {
+            Ok(arguments) => sqlx_core::query_scalar::query_statement_scalar_with(self, arguments),
+            Err(e) => sqlx_core::query_scalar::query_scalar_with_result(&self.sql, Err(e)),
+        }
+    }
 }
 
 impl<'q> sqlx_core::statement::Statement<'q> for D1Statement<'q> {
@@ -11,6 +140,10 @@ impl<'q> sqlx_core::statement::Statement<'q> for D1Statement<'q> {
     fn to_owned(&self) -> <Self::Database as sqlx_core::database::Database>::Statement<'static> {
         D1Statement::<'static> {
             sql: Cow::Owned(self.sql.clone().into_owned()),
+            parameters: self.parameters,
+            columns: Arc::clone(&self.columns),
+            #[cfg(target_arch = "wasm32")]
+            prepared: self.prepared.clone(),
         }
     }
 
@@ -23,12 +156,376 @@ impl<'q> sqlx_core::statement::Statement<'q> for D1Statement<'q> {
     ) -> Option<
         sqlx_core::Either<&[<Self::Database as sqlx_core::database::Database>::TypeInfo], usize>,
     > {
-        None
+        self.parameters.map(Either::Right)
     }
 
     fn columns(&self) -> &[<Self::Database as sqlx_core::database::Database>::Column] {
-        &[]
+        &self.columns
     }
 
     impl_statement_query!(crate::arguments::D1Arguments);
 }
+
+/// Rough kind of a SQL statement, judged by its leading keyword and `RETURNING` clause.
+///
+/// This is conservative: when unsure, it's regarded as `Unknown`, which may write.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum StatementKind {
+    /// `SELECT`, `VALUES`, `EXPLAIN` and `PRAGMA`s reading values, that read and return rows
+    Query,
+    /// `INSERT`, `UPDATE`, `DELETE`, `REPLACE` without `RETURNING`
+    Mutation,
+    /// `INSERT`, `UPDATE`, `DELETE`, `REPLACE` with `RETURNING`
+    MutationReturning,
+    /// `CREATE`, `DROP`, `ALTER`, `PRAGMA`s setting values, ... that return no rows
+    Definition,
+    /// others like `WITH`, which may be followed by either `SELECT` or a write
+    Unknown,
+}
+
+impl StatementKind {
+    /// `PRAGMA`s taking an argument but just reading
+    const READING_PRAGMAS_WITH_ARGUMENT: &[&str] = &[
+        "table_info",
+        "table_xinfo",
+        "table_list",
+        "index_info",
+        "index_xinfo",
+        "index_list",
+        "foreign_key_list",
+        "foreign_key_check",
+        "integrity_check",
+        "quick_check",
+    ];
+
+    pub(crate) fn of(sql: &str) -> Self {
+        let mut sql = sql.trim_start();
+        loop {
+            if let Some(rest) = sql.strip_prefix("--") {
+                sql = rest
+                    .split_once('\n')
+                    .map_or("", |(_, rest)| rest)
+                    .trim_start();
+            } else if let Some(rest) = sql.strip_prefix("/*") {
+                sql = rest
+                    .split_once("*/")
+                    .map_or("", |(_, rest)| rest)
+                    .trim_start();
+            } else {
+                break;
+            }
+        }
+
+        let mut words = sql.split(|c: char| !(c.is_ascii_alphanumeric() || c == '_'));
+        let leading_keyword = words.next().unwrap_or_default();
+        let is_one_of = |keywords: &[&str]| {
+            keywords
+                .iter()
+                .any(|k| leading_keyword.eq_ignore_ascii_case(k))
+        };
+
+        if is_one_of(&["SELECT", "VALUES", "EXPLAIN"]) {
+            Self::Query
+        } else if is_one_of(&["PRAGMA"]) {
+            Self::of_pragma(&sql[leading_keyword.len()..])
+        } else if is_one_of(&["INSERT", "UPDATE", "DELETE", "REPLACE"]) {
+            if words.any(|word| word.eq_ignore_ascii_case("RETURNING")) {
+                Self::MutationReturning
+            } else {
+                Self::Mutation
+            }
+        } else if is_one_of(&[
+            "CREATE",
+            "DROP",
+            "ALTER",
+            "BEGIN",
+            "COMMIT",
+            "END",
+            "ROLLBACK",
+            "SAVEPOINT",
+            "RELEASE",
+            "ANALYZE",
+            "REINDEX",
+            "VACUUM",
+            "ATTACH",
+            "DETACH",
+        ]) {
+            Self::Definition
+        } else {
+            Self::Unknown
+        }
+    }
+
+    /// `pragma` is what follows `PRAGMA`, like ` schema.name = value` or ` name(argument)`.
+    fn of_pragma(pragma: &str) -> Self {
+        let (name, argument) = match pragma.find(['=', '(']) {
+            Some(i) => (&pragma[..i], Some(&pragma[i..i + 1])),
+            None => (pragma, None),
+        };
+        let name = name.rsplit('.').next().unwrap_or_default().trim();
+        let name = name.trim_end_matches(';').trim_end();
+
+        let is_reading = match argument {
+            None => !name.eq_ignore_ascii_case("optimize"),
+            Some("(") => Self::READING_PRAGMAS_WITH_ARGUMENT
+                .iter()
+                .any(|p| name.eq_ignore_ascii_case(p)),
+            Some(_) => false,
+        };
+        if is_reading {
+            Self::Query
+        } else {
+            Self::Definition
+        }
+    }
+
+    #[cfg(target_arch = "wasm32")]
+    pub(crate) const fn returns_rows(self) -> bool {
+        matches!(self, Self::Query | Self::MutationReturning | Self::Unknown)
+    }
+
+    /// whether executing it twice has the same effect as once, allowing only
+    /// the statements known to be read-only
+    #[cfg(target_arch = "wasm32")]
+    pub(crate) const fn is_idempotent(self) -> bool {
+        matches!(self, Self::Query)
+    }
+}
+
+/// Whether the SQL has multiple statements, without splitting it
+/// when there's no `;` except for a trailing one.
+#[cfg_attr(not(target_arch = "wasm32"), allow(dead_code))]
+pub(crate) fn has_multiple_statements(sql: &str) -> bool {
+    sql.trim_end().trim_end_matches(';').contains(';') && split_statements(sql).len() > 1
+}
+
+/// Splits the SQL into statements by `;`, with comments removed and line breaks
+/// outside of quotes replaced with spaces. `;`s in `CREATE TRIGGER ... BEGIN ... END`
+/// are kept as they are.
+pub(crate) fn split_statements(sql: &str) -> Vec<String> {
+    fn words(s: &str) -> impl Iterator<Item = &str> {
+        s.split(|c: char| !(c.is_ascii_alphanumeric() || c == '_'))
+            .filter(|w| !w.is_empty())
+    }
+
+    /// `statement` with quoted strings and identifiers replaced with spaces.
+    fn unquoted(statement: &str) -> String {
+        let (mut unquoted, mut chars) = (String::with_capacity(statement.len()), statement.chars());
+        while let Some(c) = chars.next() {
+            match c {
+                '\'' | '"' | '`' | '[' => {
+                    let close = if c == '[' { ']' } else { c };
+                    chars.by_ref().find(|c| *c == close);
+                    unquoted.push(' ');
+                }
+                _ => unquoted.push(c),
+            }
+        }
+        unquoted
+    }
+
+    /// Whether the statement is `CREATE TRIGGER` whose `BEGIN ... END` is not closed yet,
+    /// taking `CASE ... END`s in the body into account.
+    fn is_in_trigger_body(statement: &str) -> bool {
+        let unquoted = unquoted(statement);
+        let words = words(&unquoted).collect::<Vec<_>>();
+        if !(words
+            .first()
+            .is_some_and(|w| w.eq_ignore_ascii_case("CREATE"))
+            && words
+                .iter()
+                .skip(1)
+                .take(2)
+                .any(|w| w.eq_ignore_ascii_case("TRIGGER")))
+        {
+            return false;
+        }
+
+        let Some(begin) = words.iter().position(|w| w.eq_ignore_ascii_case("BEGIN")) else {
+            return true;
+        };
+        let mut depth = 1_usize;
+        for word in &words[begin + 1..] {
+            if word.eq_ignore_ascii_case("CASE") {
+                depth += 1;
+            } else if word.eq_ignore_ascii_case("END") {
+                depth -= 1;
+                if depth == 0 {
+                    return false;
+                }
+            }
+        }
+        true
+    }
+
+    let (mut statements, mut current) = (Vec::new(), String::new());
+    let mut flush = |current: &mut String| {
+        let statement = current.trim();
+        if !statement.is_empty() {
+            statements.push(statement.to_owned());
+        }
+        current.clear();
+    };
+
+    let mut chars = sql.chars().peekable();
+    while let Some(c) = chars.next() {
+        match c {
+            '\'' | '"' | '`' | '[' => {
+                let close = if c == '[' { ']' } else { c };
+                current.push(c);
+                for c in chars.by_ref() {
+                    current.push(c);
+                    if c == close {
+                        break;
+                    }
+                }
+            }
+            '-' if chars.peek() == Some(&'-') => {
+                for c in chars.by_ref() {
+                    if c == '\n' {
+                        break;
+                    }
+                }
+                current.push(' ');
+            }
+            '/' if chars.peek() == Some(&'*') => {
+                chars.next();
+                let mut prev = '\0';
+                for c in chars.by_ref() {
+                    if prev == '*' && c == '/' {
+                        break;
+                    }
+                    prev = c;
+                }
+                current.push(' ');
+            }
+            ';' if is_in_trigger_body(&current) => current.push(';'),
+            ';' => flush(&mut current),
+            '\n' | '\r' => current.push(' '),
+            _ => current.push(c),
+        }
+    }
+    flush(&mut current);
+
+    statements
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{has_multiple_statements, split_statements};
+
+    #[test]
+    fn split_statements_by_semicolons() {
+        assert_eq!(
+            split_statements("SELECT 1;\nSELECT 2;\n"),
+            ["SELECT 1", "SELECT 2"]
+        );
+        assert_eq!(split_statements("SELECT 1"), ["SELECT 1"]);
+        assert_eq!(split_statements(" ;\n; "), [] as [&str; 0]);
+    }
+
+    #[test]
+    fn split_statements_with_quotes() {
+        assert_eq!(
+            split_statements("INSERT INTO t VALUES ('a;b', \"c;d\"); SELECT `e;f`, [g;h] FROM t"),
+            [
+                "INSERT INTO t VALUES ('a;b', \"c;d\")",
+                "SELECT `e;f`, [g;h] FROM t"
+            ]
+        );
+        assert_eq!(
+            split_statements("SELECT 'line\nbreak'"),
+            ["SELECT 'line\nbreak'"]
+        );
+    }
+
+    #[test]
+    fn split_statements_with_comments() {
+        assert_eq!(
+            split_statements("-- a; comment\nSELECT 1; -- trailing;\nSELECT 2"),
+            ["SELECT 1", "SELECT 2"]
+        );
+        assert_eq!(
+            split_statements("/* a;\nblock */ SELECT 1 /* ; */; SELECT 2;"),
+            ["SELECT 1", "SELECT 2"]
+        );
+    }
+
+    #[test]
+    fn split_statements_with_trigger() {
+        assert_eq!(
+            split_statements(
+                "CREATE TRIGGER t AFTER INSERT ON a BEGIN\n\
+                \x20   INSERT INTO b VALUES (1);\n\
+                \x20   DELETE FROM c;\n\
+                END;\n\
+                CREATE TEMP TRIGGER u AFTER DELETE ON a BEGIN DELETE FROM b; END;\n\
+                SELECT 1;"
+            ),
+            [
+                "CREATE TRIGGER t AFTER INSERT ON a BEGIN     INSERT INTO b VALUES (1);     DELETE FROM c; END",
+                "CREATE TEMP TRIGGER u AFTER DELETE ON a BEGIN DELETE FROM b; END",
+                "SELECT 1"
+            ]
+        );
+    }
+
+    #[test]
+    fn split_statements_with_trigger_case() {
+        assert_eq!(
+            split_statements(
+                "CREATE TRIGGER tr AFTER INSERT ON t BEGIN \
+                INSERT INTO u VALUES (CASE WHEN new.x='q' THEN 1 ELSE 0 END); \
+                INSERT INTO u VALUES (2); \
+                END;\n\
+                CREATE TRIGGER tr2 AFTER INSERT ON t WHEN CASE new.x WHEN 'end' THEN 1 END BEGIN \
+                INSERT INTO u VALUES ('end'); \
+                END;\n\
+                SELECT 1;"
+            ),
+            [
+                "CREATE TRIGGER tr AFTER INSERT ON t BEGIN INSERT INTO u VALUES (CASE WHEN new.x='q' THEN 1 ELSE 0 END); INSERT INTO u VALUES (2); END",
+                "CREATE TRIGGER tr2 AFTER INSERT ON t WHEN CASE new.x WHEN 'end' THEN 1 END BEGIN INSERT INTO u VALUES ('end'); END",
+                "SELECT 1"
+            ]
+        );
+    }
+
+    #[test]
+    fn judge_statement_kinds() {
+        use super::StatementKind::{self, *};
+
+        for (sql, kind) in [
+            ("SELECT * FROM users", Query),
+            ("  -- comment\n/* block */ select 1", Query),
+            ("VALUES (1), (2)", Query),
+            ("EXPLAIN QUERY PLAN SELECT 1", Query),
+            ("PRAGMA table_list", Query),
+            ("PRAGMA main.table_info(\"users\")", Query),
+            ("PRAGMA foreign_keys;", Query),
+            ("PRAGMA foreign_keys = ON", Definition),
+            ("PRAGMA defer_foreign_keys(true)", Definition),
+            ("PRAGMA optimize", Definition),
+            ("INSERT INTO users (name) VALUES (?)", Mutation),
+            ("DELETE FROM users RETURNING id", MutationReturning),
+            ("CREATE TABLE users (id INTEGER)", Definition),
+            ("WITH t AS (SELECT 1) SELECT * FROM t", Unknown),
+            (
+                "WITH t AS (SELECT 1) INSERT INTO users SELECT * FROM t",
+                Unknown,
+            ),
+            ("", Unknown),
+        ] {
+            assert_eq!(StatementKind::of(sql), kind, "{sql}");
+        }
+    }
+
+    #[test]
+    fn detect_multiple_statements() {
+        assert!(!has_multiple_statements("SELECT 1"));
+        assert!(!has_multiple_statements("SELECT 1;\n"));
+        assert!(!has_multiple_statements("SELECT ';'"));
+        assert!(!has_multiple_statements("SELECT 1; -- comment"));
+        assert!(has_multiple_statements("SELECT 1; SELECT 2"));
+    }
+}
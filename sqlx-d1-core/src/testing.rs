@@ -0,0 +1,46 @@
+//! Runtime of `#[sqlx_d1::test]`.
+//!
+//! ref: <https://github.com/launchbadge/sqlx/blob/6651d2df72586519708147d96e1ec1054a898c1e/sqlx-core/src/testing/mod.rs>
+
+#![cfg(all(not(target_arch = "wasm32"), feature = "migrate"))]
+
+use crate::{D1ConnectOptions, D1Connection, D1Migrator};
+
+/// SQL script embedded by `#[sqlx_d1::test]`.
+pub struct TestScript {
+    /// path for error messages
+    pub path: &'static str,
+    pub contents: &'static str,
+}
+
+pub struct TestArgs {
+    /// migrations, recorded in `d1_migrations` as `D1Migrator::run` does
+    pub migrator: D1Migrator,
+    /// fixtures applied after the migrations, in the order of application
+    pub fixtures: &'static [TestScript],
+}
+
+/// Run `test_fn` with a connection to a fresh in-memory database,
+/// where the migrations and then the fixtures are applied.
+pub fn run_test<Fut: std::future::Future>(
+    args: TestArgs,
+    test_fn: impl FnOnce(D1Connection) -> Fut,
+) -> Fut::Output {
+    futures_lite::future::block_on(async move {
+        let conn = D1ConnectOptions::sqlite_in_memory()
+            .connect()
+            .await
+            .unwrap_or_else(|e| panic!("failed to create a test database: {e}"));
+
+        if let Err(e) = args.migrator.run(&conn).await {
+            panic!("failed to apply migrations: {e}");
+        }
+        for TestScript { path, contents } in args.fixtures {
+            if let Err(e) = conn.exec_script(contents).await {
+                panic!("failed to apply fixture `{path}`: {e}");
+            }
+        }
+
+        test_fn(conn).await
+    })
+}
@@ -1,6 +1,69 @@
+//! D1 doesn't support `BEGIN` / `COMMIT` / `ROLLBACK`, and the only way to
+//! execute multiple statements atomically is `batch`.
+//!
+//! So a transaction on `D1Connection` defers the write statements executed on it
+//! and sends them as a single batch on commit, or just drops them on rollback.
+//! Reading after some writes are deferred is rejected with an error,
+//! because it can't see the deferred writes. So are statements not known to be
+//! reads or writes (like `WITH ...`), and writes bypassing the transaction
+//! through `&D1Connection`, `batch` or `exec_script`.
+//!
+//! **NOTE**: as the deferred writes are not executed until commit, `execute` of
+//! them returns a placeholder `D1QueryResult::default()`, whose `rows_affected`
+//! is `0` whatever the statement changes. Commit returns no results either, so
+//! use `D1Connection::batch` directly when the results are needed.
+
+use crate::{D1, D1Connection, arguments::D1Arguments, statement::StatementKind};
+use sqlx_core::executor::Execute;
 use std::borrow::Cow;
 
-use crate::D1Connection;
+/// Write statements deferred in a transaction, sent as a batch on commit.
+#[derive(Default)]
+pub(crate) struct DeferredStatements(Vec<(String, D1Arguments)>);
+
+impl DeferredStatements {
+    /// Defers the query if it's a write one.
+    ///
+    /// Returns `None` if the query should be executed right now.
+    pub(crate) fn defer<'q>(
+        &mut self,
+        query: &mut impl Execute<'q, D1>,
+    ) -> Option<Result<(), sqlx_core::Error>> {
+        match StatementKind::of(query.sql()) {
+            StatementKind::Mutation | StatementKind::Definition => Some(
+                crate::statement::take_checked_arguments(query).map(|arguments| {
+                    self.0
+                        .push((query.sql().to_owned(), arguments.unwrap_or_default()))
+                }),
+            ),
+            _ => self.check_immediate(query.sql()).err().map(Err),
+        }
+    }
+
+    /// Checks if the statement can be executed right now without being deferred,
+    /// that is only for reads before any writes are deferred.
+    pub(crate) fn check_immediate(&self, sql: &str) -> Result<(), sqlx_core::Error> {
+        let reason = match StatementKind::of(sql) {
+            StatementKind::Query if self.0.is_empty() => return Ok(()),
+            StatementKind::Query => {
+                "can't read after some writes because they are deferred until commit"
+            }
+            StatementKind::MutationReturning => {
+                "can't execute a write returning rows because writes are deferred until commit"
+            }
+            StatementKind::Unknown => {
+                "can't execute a statement not known to be a read or a write \
+                (like `WITH ...`) because writes are deferred until commit"
+            }
+            StatementKind::Mutation | StatementKind::Definition => {
+                "defers writes only when they're executed on `&mut` of the transaction"
+            }
+        };
+        Err(sqlx_core::Error::InvalidArgument(format!(
+            "D1 transaction {reason}: `{sql}`"
+        )))
+    }
+}
 
 pub struct D1TransactionManager;
 
@@ -8,51 +71,188 @@ impl sqlx_core::transaction::TransactionManager for D1TransactionManager {
     type Database = crate::D1;
 
     fn begin<'conn>(
-        #[allow(unused)]
         conn: &'conn mut <Self::Database as sqlx_core::database::Database>::Connection,
-        #[allow(unused)] statement: Option<Cow<'static, str>>,
+        statement: Option<Cow<'static, str>>,
     ) -> crate::ResultFuture<'conn, ()> {
-        #[cfg(not(target_arch = "wasm32"))]
-        {
-            unreachable!("Native `TransactionManager` impl")
-        }
-        #[cfg(target_arch = "wasm32")]
-        {
-            Box::pin(async { Ok(()) })
-        }
+        Box::pin(async move {
+            if conn.transaction.is_some() {
+                return Err(sqlx_core::Error::InvalidArgument(
+                    "D1 doesn't support nested transactions".into(),
+                ));
+            }
+            if statement.is_some() {
+                return Err(sqlx_core::Error::InvalidArgument(
+                    "D1 doesn't support custom `BEGIN` statements".into(),
+                ));
+            }
+
+            conn.transaction = Some(DeferredStatements::default());
+            Ok(())
+        })
     }
 
     fn commit(
-        #[allow(unused)] conn: &mut <Self::Database as sqlx_core::database::Database>::Connection,
+        conn: &mut <Self::Database as sqlx_core::database::Database>::Connection,
     ) -> crate::ResultFuture<'_, ()> {
-        #[cfg(not(target_arch = "wasm32"))]
-        {
-            unreachable!("Native `TransactionManager` impl")
-        }
-        #[cfg(target_arch = "wasm32")]
-        {
-            Box::pin(async { Ok(()) })
-        }
+        Box::pin(worker::send::SendFuture::new(async move {
+            let Some(DeferredStatements(statements)) = conn.transaction.take() else {
+                return Ok(());
+            };
+            if statements.is_empty() {
+                return Ok(());
+            }
+
+            let (sqls, arguments): (Vec<_>, Vec<_>) = statements.into_iter().unzip();
+            conn.batch(
+                sqls.iter()
+                    .zip(arguments)
+                    .map(|(sql, arguments)| crate::query_with(sql, arguments)),
+            )
+            .await?;
+
+            Ok(())
+        }))
     }
 
     fn rollback(
-        #[allow(unused)] conn: &mut <Self::Database as sqlx_core::database::Database>::Connection,
+        conn: &mut <Self::Database as sqlx_core::database::Database>::Connection,
     ) -> crate::ResultFuture<'_, ()> {
-        #[cfg(not(target_arch = "wasm32"))]
-        {
-            unreachable!("Native `TransactionManager` impl")
-        }
-        #[cfg(target_arch = "wasm32")]
-        {
-            Box::pin(async { Ok(()) })
-        }
+        conn.transaction = None;
+        Box::pin(async { Ok(()) })
+    }
+
+    fn start_rollback(conn: &mut <Self::Database as sqlx_core::database::Database>::Connection) {
+        conn.transaction = None;
     }
 
-    fn start_rollback(_: &mut <Self::Database as sqlx_core::database::Database>::Connection) {
-        /* do nothing */
+    fn get_transaction_depth(conn: &D1Connection) -> usize {
+        conn.transaction.is_some() as usize
     }
+}
+
+#[cfg(all(test, not(target_arch = "wasm32")))]
+mod tests {
+    use crate::{D1ConnectOptions, D1Connection};
+    use sqlx_core::{connection::Connection as _, executor::Executor as _};
+
+    async fn connect() -> D1Connection {
+        let conn = D1ConnectOptions::sqlite_in_memory()
+            .connect()
+            .await
+            .unwrap();
+        conn.exec_script("CREATE TABLE users (id INTEGER PRIMARY KEY, name TEXT NOT NULL UNIQUE);")
+            .await
+            .unwrap();
+        conn
+    }
+
+    async fn count_users(conn: &D1Connection) -> i64 {
+        crate::query_scalar("SELECT COUNT(*) FROM users")
+            .fetch_one(conn)
+            .await
+            .unwrap()
+    }
+
+    #[test]
+    fn commit_sends_deferred_writes() {
+        futures_lite::future::block_on(async {
+            let mut conn = connect().await;
+            let other = conn.clone();
+
+            let mut tx = conn.begin().await.unwrap();
+            assert_eq!(count_users(&other).await, 0);
+            let result = (&mut *tx)
+                .execute("INSERT INTO users (name) VALUES ('alice'), ('bob')")
+                .await
+                .unwrap();
+            /* placeholder as the write is deferred */
+            assert_eq!(result.rows_affected, 0);
+            assert_eq!(count_users(&other).await, 0);
+
+            tx.commit().await.unwrap();
+            assert_eq!(count_users(&other).await, 2);
+        });
+    }
+
+    #[test]
+    fn failed_commit_applies_nothing() {
+        futures_lite::future::block_on(async {
+            let mut conn = connect().await;
+
+            let mut tx = conn.begin().await.unwrap();
+            (&mut *tx)
+                .execute("INSERT INTO users (name) VALUES ('alice')")
+                .await
+                .unwrap();
+            (&mut *tx)
+                .execute("INSERT INTO users (name) VALUES ('alice')")
+                .await
+                .unwrap();
+            assert!(tx.commit().await.is_err());
+
+            assert_eq!(count_users(&conn).await, 0);
+            let tx = conn.begin().await.unwrap();
+            tx.commit().await.unwrap();
+        });
+    }
+
+    #[test]
+    fn drop_rolls_back() {
+        futures_lite::future::block_on(async {
+            let mut conn = connect().await;
+
+            {
+                let mut tx = conn.begin().await.unwrap();
+                (&mut *tx)
+                    .execute("INSERT INTO users (name) VALUES ('alice')")
+                    .await
+                    .unwrap();
+            }
+            assert_eq!(count_users(&conn).await, 0);
+
+            let mut tx = conn.begin().await.unwrap();
+            (&mut *tx)
+                .execute("INSERT INTO users (name) VALUES ('bob')")
+                .await
+                .unwrap();
+            tx.rollback().await.unwrap();
+            assert_eq!(count_users(&conn).await, 0);
+        });
+    }
+
+    #[test]
+    fn read_after_write_is_rejected() {
+        futures_lite::future::block_on(async {
+            let mut conn = connect().await;
+
+            let mut tx = conn.begin().await.unwrap();
+            crate::query("SELECT * FROM users")
+                .fetch_all(&mut *tx)
+                .await
+                .unwrap();
+            (&mut *tx)
+                .execute("INSERT INTO users (name) VALUES ('alice')")
+                .await
+                .unwrap();
+
+            for sql in [
+                "SELECT * FROM users",
+                "INSERT INTO users (name) VALUES ('bob') RETURNING id",
+                "WITH t AS (SELECT 1) SELECT * FROM t",
+            ] {
+                assert!(matches!(
+                    crate::query(sql).fetch_all(&mut *tx).await,
+                    Err(sqlx_core::Error::InvalidArgument(_))
+                ));
+            }
+            /* writes bypassing the transaction */
+            assert!(matches!(
+                (&*tx).execute("DELETE FROM users").await,
+                Err(sqlx_core::Error::InvalidArgument(_))
+            ));
 
-    fn get_transaction_depth(#[allow(unused)] conn: &D1Connection) -> usize {
-        1 // dummy since this will never be used because d1 does not support transactions
+            tx.commit().await.unwrap();
+            assert_eq!(count_users(&conn).await, 1);
+        });
     }
 }
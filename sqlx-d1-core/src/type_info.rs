@@ -28,17 +28,22 @@ impl D1TypeInfo {
     pub(crate) fn from_sqlite(sqlite_type_info: sqlx_sqlite::SqliteTypeInfo) -> Self {
         use sqlx_core::type_info::TypeInfo as _;
 
+        Self::from_name(sqlite_type_info.name())
+    }
+
+    /// from the name of SQLite's declared type, or of `D1Type` serialized by the `offline` feature
+    pub(crate) fn from_name(name: &str) -> Self {
         /* ref: <https://github.com/launchbadge/sqlx/blob/25efb2f7f410e0f0aa3fee1d8467429066dbcdf8/sqlx-sqlite/src/type_info.rs#L56-L71> */
-        match sqlite_type_info.name() {
+        match name {
             "NULL" => Self::null(),
             "TEXT" => Self::text(),
             "REAL" => Self::real(),
             "BLOB" => Self::blob(),
             "INTEGER" | "NUMERIC" => Self::integer(),
             "BOOLEAN" => Self::boolean(),
-            "DATE" => Self::date(),
-            "TIME" => Self::time(),
-            "DATETIME" => Self::datetime(),
+            "DATE" => Self(D1Type::Date),
+            "TIME" => Self(D1Type::Time),
+            "DATETIME" => Self(D1Type::Datetime),
             _ => *Self::unknown(),
         }
     }
@@ -63,15 +68,15 @@ impl D1TypeInfo {
         Self(D1Type::Boolean)
     }
 
-    #[cfg(any(feature = "chrono", not(target_arch = "wasm32")))]
+    #[cfg(feature = "chrono")]
     pub(crate) const fn date() -> Self {
         Self(D1Type::Date)
     }
-    #[cfg(any(feature = "chrono", not(target_arch = "wasm32")))]
+    #[cfg(feature = "chrono")]
     pub(crate) const fn time() -> Self {
         Self(D1Type::Time)
     }
-    #[cfg(any(feature = "chrono", not(target_arch = "wasm32")))]
+    #[cfg(feature = "chrono")]
     pub(crate) const fn datetime() -> Self {
         Self(D1Type::Datetime)
     }
@@ -81,7 +86,8 @@ impl D1TypeInfo {
         &Self(D1Type::Blob)
     }
 
-    pub(crate) fn from_raw(raw: &worker::wasm_bindgen::JsValue) -> Self {
+    #[cfg(target_arch = "wasm32")]
+    pub(crate) fn from_raw(raw: &crate::value::RawValue) -> Self {
         if raw.is_null() || raw.is_undefined() {
             Self::null()
         } else if raw.is_string() {
@@ -98,6 +104,21 @@ impl D1TypeInfo {
             *Self::unknown()
         }
     }
+
+    #[cfg(not(target_arch = "wasm32"))]
+    pub(crate) fn from_raw(raw: &crate::value::RawValue) -> Self {
+        use serde_json::Value;
+
+        match raw {
+            Value::Null => Self::null(),
+            Value::String(_) => Self::text(),
+            Value::Bool(_) => Self::integer(),
+            Value::Number(n) if n.is_f64() => Self::real(),
+            Value::Number(_) => Self::integer(),
+            Value::Array(_) => Self::blob(),
+            Value::Object(_) => *Self::unknown(),
+        }
+    }
 }
 
 impl std::fmt::Display for D1TypeInfo {
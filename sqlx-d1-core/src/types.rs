@@ -4,7 +4,11 @@ use crate::{D1, error::D1Error, type_info::D1TypeInfo, value::D1Value};
 use sqlx_core::decode::Decode;
 use sqlx_core::encode::{Encode, IsNull};
 use sqlx_core::types::Type;
-use worker::{serde_wasm_bindgen, wasm_bindgen::JsValue};
+#[cfg(target_arch = "wasm32")]
+use worker::serde_wasm_bindgen as serde_raw;
+/* `RawValue` is `serde_json::Value` on native targets */
+#[cfg(not(target_arch = "wasm32"))]
+use serde_json as serde_raw;
 
 /* compile-time compatibility check support for `sqlx::query_*!` macro's internal */
 
@@ -104,7 +108,7 @@ macro_rules! serialize {
             buf: &mut <D1 as sqlx_core::database::Database>::ArgumentBuffer<$q>,
         ) -> Result<IsNull, sqlx_core::error::BoxDynError> {
             buf.push(D1Value::from(
-                serde_wasm_bindgen::to_value(self).map_err(D1Error::from_rust)?,
+                serde_raw::to_value(self).map_err(D1Error::from_rust)?,
             ));
             Ok(IsNull::No)
         }
@@ -116,7 +120,7 @@ macro_rules! deserialize {
         fn decode(
             value: <D1 as sqlx_core::database::Database>::ValueRef<'_>,
         ) -> Result<Self, sqlx_core::error::BoxDynError> {
-            Ok(serde_wasm_bindgen::from_value(value.into()).map_err(D1Error::from_rust)?)
+            Ok(serde_raw::from_value(value.into()).map_err(D1Error::from_rust)?)
         }
     };
 }
@@ -196,7 +200,7 @@ const _: (/* bool */) = {
             &self,
             buf: &mut <D1 as sqlx_core::database::Database>::ArgumentBuffer<'q>,
         ) -> Result<IsNull, sqlx_core::error::BoxDynError> {
-            buf.push(D1Value::from(JsValue::from_f64(if *self {1.} else {0.})));
+            buf.push(D1Value::from(crate::value::RawValue::from(u8::from(*self))));
             Ok(IsNull::No)
         }
     }
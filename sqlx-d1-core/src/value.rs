@@ -1,11 +1,26 @@
-pub struct D1Value(worker::send::SendWrapper<worker::wasm_bindgen::JsValue>);
+/// Raw value D1 takes and returns: `JsValue` on Workers.
+#[cfg(target_arch = "wasm32")]
+pub(crate) type RawValue = worker::wasm_bindgen::JsValue;
+/// Raw value D1 takes and returns: `serde_json::Value` as the counterpart of
+/// `JsValue` on native targets, where `sqlx_sqlite` backs `D1Connection`.
+#[cfg(not(target_arch = "wasm32"))]
+pub(crate) type RawValue = serde_json::Value;
+
+pub struct D1Value(worker::send::SendWrapper<RawValue>);
 const _: () = {
     impl std::fmt::Debug for D1Value {
         fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-            f.debug_struct("D1Value")
-                .field("typeof", &self.0.0.js_typeof().as_string().unwrap())
-                .field("value", &self.0.0)
-                .finish()
+            #[cfg(target_arch = "wasm32")]
+            {
+                f.debug_struct("D1Value")
+                    .field("typeof", &self.0.0.js_typeof().as_string().unwrap())
+                    .field("value", &self.0.0)
+                    .finish()
+            }
+            #[cfg(not(target_arch = "wasm32"))]
+            {
+                f.debug_tuple("D1Value").field(&self.0.0).finish()
+            }
         }
     }
 
@@ -24,24 +39,63 @@ const _: () = {
         }
 
         fn is_null(&self) -> bool {
-            self.0.loose_eq(&worker::wasm_bindgen::JsValue::null())
+            #[cfg(target_arch = "wasm32")]
+            {
+                self.0.loose_eq(&RawValue::null())
+            }
+            #[cfg(not(target_arch = "wasm32"))]
+            {
+                self.0.is_null()
+            }
         }
     }
 
-    impl From<worker::wasm_bindgen::JsValue> for D1Value {
-        fn from(value: worker::wasm_bindgen::JsValue) -> Self {
+    impl From<RawValue> for D1Value {
+        fn from(value: RawValue) -> Self {
             Self(worker::send::SendWrapper(value))
         }
     }
 
     impl D1Value {
         pub(crate) fn null() -> Self {
-            Self::from(worker::wasm_bindgen::JsValue::null())
+            #[cfg(target_arch = "wasm32")]
+            {
+                Self::from(RawValue::null())
+            }
+            #[cfg(not(target_arch = "wasm32"))]
+            {
+                Self::from(RawValue::Null)
+            }
+        }
+    }
+
+    #[cfg(not(target_arch = "wasm32"))]
+    impl D1Value {
+        pub(crate) fn from_sqlite(
+            value: sqlx_sqlite::SqliteValueRef<'_>,
+        ) -> Result<Self, sqlx_core::Error> {
+            use sqlx_core::{decode::Decode, type_info::TypeInfo as _, value::ValueRef as _};
+            use sqlx_sqlite::Sqlite;
+
+            if value.is_null() {
+                return Ok(Self::null());
+            }
+
+            /* the storage class of the value itself, not the declared type of the column */
+            let storage_class = value.type_info().name().to_owned();
+            let raw = match &*storage_class {
+                "INTEGER" => <i64 as Decode<Sqlite>>::decode(value).map(RawValue::from),
+                "REAL" => <f64 as Decode<Sqlite>>::decode(value).map(RawValue::from),
+                "TEXT" => <String as Decode<Sqlite>>::decode(value).map(RawValue::from),
+                _ => <Vec<u8> as Decode<Sqlite>>::decode(value).map(RawValue::from),
+            }
+            .map_err(sqlx_core::Error::Decode)?;
+            Ok(Self::from(raw))
         }
     }
 };
 
-pub struct D1ValueRef<'r>(worker::send::SendWrapper<&'r worker::wasm_bindgen::JsValue>);
+pub struct D1ValueRef<'r>(worker::send::SendWrapper<&'r RawValue>);
 const _: () = {
     impl<'r> sqlx_core::value::ValueRef<'r> for D1ValueRef<'r> {
         type Database = crate::D1;
@@ -58,7 +112,14 @@ const _: () = {
         }
 
         fn is_null(&self) -> bool {
-            self.0.loose_eq(&worker::wasm_bindgen::JsValue::null())
+            #[cfg(target_arch = "wasm32")]
+            {
+                self.0.loose_eq(&RawValue::null())
+            }
+            #[cfg(not(target_arch = "wasm32"))]
+            {
+                self.0.is_null()
+            }
         }
     }
 
@@ -69,21 +130,30 @@ const _: () = {
     }
 
     impl<'r> std::ops::Deref for D1ValueRef<'r> {
-        type Target = worker::wasm_bindgen::JsValue;
+        type Target = RawValue;
 
         fn deref(&self) -> &Self::Target {
             &self.0
         }
     }
 
-    impl<'r> From<D1ValueRef<'r>> for worker::wasm_bindgen::JsValue {
-        fn from(d1valueref: D1ValueRef<'r>) -> worker::wasm_bindgen::JsValue {
+    /// same as `JsValue::as_string`
+    #[cfg(not(target_arch = "wasm32"))]
+    impl D1ValueRef<'_> {
+        #[cfg_attr(not(feature = "chrono"), allow(dead_code))]
+        pub(crate) fn as_string(&self) -> Option<String> {
+            self.0.0.as_str().map(String::from)
+        }
+    }
+
+    impl<'r> From<D1ValueRef<'r>> for RawValue {
+        fn from(d1valueref: D1ValueRef<'r>) -> RawValue {
             d1valueref.0.0.clone()
         }
     }
 
-    impl<'r> From<&'r worker::wasm_bindgen::JsValue> for D1ValueRef<'r> {
-        fn from(value: &'r worker::wasm_bindgen::JsValue) -> Self {
+    impl<'r> From<&'r RawValue> for D1ValueRef<'r> {
+        fn from(value: &'r RawValue) -> Self {
             Self(worker::send::SendWrapper(value))
         }
     }
@@ -0,0 +1,84 @@
+//! ref: <https://github.com/launchbadge/sqlx/blob/6651d2df72586519708147d96e1ec1054a898c1e/sqlx-macros-core/src/migrate.rs>
+
+use proc_macro2::{Span, TokenStream};
+use quote::quote;
+use std::path::PathBuf;
+use syn::LitStr;
+
+pub(super) fn expand_migrate(input: TokenStream) -> syn::Result<TokenStream> {
+    let dir = if input.is_empty() {
+        LitStr::new("migrations", Span::call_site())
+    } else {
+        syn::parse2::<LitStr>(input)?
+    };
+    expand_migrator(&dir)
+}
+
+/// `D1Migrator` embedding the migrations in `dir` relative to the package root.
+pub(super) fn expand_migrator(dir: &LitStr) -> syn::Result<TokenStream> {
+    let migrations = migration_files(dir)?
+        .into_iter()
+        .map(|path| {
+            use sha2::{Digest, Sha384};
+
+            let name = file_name_of(&path);
+            let sql = std::fs::read_to_string(&path).map_err(|e| {
+                syn::Error::new(
+                    dir.span(),
+                    format!("failed to read migration `{}`: {e}", path.display()),
+                )
+            })?;
+            let checksum = Sha384::digest(sql.as_bytes());
+            let checksum = checksum.iter();
+            let path = path.display().to_string();
+            Ok(quote! {
+                ::sqlx_d1::D1Migration {
+                    name: ::std::borrow::Cow::Borrowed(#name),
+                    sql: ::std::borrow::Cow::Borrowed(::core::include_str!(#path)),
+                    checksum: ::std::borrow::Cow::Borrowed(&[#(#checksum),*]),
+                }
+            })
+        })
+        .collect::<syn::Result<Vec<_>>>()?;
+
+    Ok(quote! {
+        ::sqlx_d1::D1Migrator {
+            migrations: ::std::borrow::Cow::Borrowed(&[#(#migrations),*]),
+            migrations_table: ::std::borrow::Cow::Borrowed("d1_migrations"),
+        }
+    })
+}
+
+/// Path relative to the package root.
+pub(super) fn package_path(relative: &str) -> PathBuf {
+    PathBuf::from(std::env::var("CARGO_MANIFEST_DIR").expect("`CARGO_MANIFEST_DIR` must be set"))
+        .join(relative)
+}
+
+fn file_name_of(path: &std::path::Path) -> String {
+    path.file_name()
+        .map(|name| name.to_string_lossy().into_owned())
+        .unwrap_or_default()
+}
+
+/// `.sql` files in the migrations directory `dir` relative to the package root,
+/// sorted by the names like `wrangler d1 migrations apply` does.
+fn migration_files(dir: &LitStr) -> syn::Result<Vec<PathBuf>> {
+    let dir_path = package_path(&dir.value());
+
+    let mut files = std::fs::read_dir(&dir_path)
+        .map_err(|e| {
+            syn::Error::new(
+                dir.span(),
+                format!(
+                    "failed to read migrations directory `{}`: {e}",
+                    dir_path.display()
+                ),
+            )
+        })?
+        .filter_map(|r| r.as_ref().ok().map(|e| e.path()))
+        .filter(|p| p.is_file() && p.extension().is_some_and(|x| x == "sql"))
+        .collect::<Vec<_>>();
+    files.sort();
+    Ok(files)
+}
@@ -112,6 +112,60 @@ impl Parse for QueryMacroInput {
 }
 
 impl QueryMacroInput {
+    /// `static` of `D1Statement` embedding the describe data, to carry the
+    /// number of parameters and the columns to runtime.
+    pub(super) fn quote_statement_with(
+        &self,
+        statement_ident: &Ident,
+        describe: &sqlx_core::describe::Describe<sqlx_d1_core::D1>,
+    ) -> syn::Result<TokenStream> {
+        use sqlx_core::column::Column as _;
+
+        // if this query came from a file, use `include_str!()` to tell the compiler where it came from
+        let sql = if let Some(path) = &self.file_path {
+            quote_spanned! { self.src_span => include_str!(#path) }
+        } else {
+            let sql = &self.sql;
+            quote! { #sql }
+        };
+
+        let parameters = match describe.parameters() {
+            None => quote! { ::core::option::Option::None },
+            Some(sqlx_core::Either::Left(params)) => {
+                let n = params.len();
+                quote! { ::core::option::Option::Some(#n) }
+            }
+            Some(sqlx_core::Either::Right(n)) => quote! { ::core::option::Option::Some(#n) },
+        };
+
+        let columns = describe
+            .columns()
+            .iter()
+            .map(|column| {
+                let name = column.name();
+                let type_name = ::serde_json::to_value(column.type_info())
+                    .ok()
+                    .and_then(|v| v.as_str().map(String::from))
+                    .ok_or_else(|| {
+                        syn::Error::new(
+                            self.src_span,
+                            format!("failed to serialize the type of column `{name}`"),
+                        )
+                    })?;
+                syn::Result::Ok(quote! { (#name, #type_name) })
+            })
+            .collect::<syn::Result<Vec<_>>>()?;
+
+        Ok(quote! {
+            static #statement_ident: ::std::sync::LazyLock<::sqlx_d1::D1Statement<'static>> =
+                ::std::sync::LazyLock::new(|| ::sqlx_d1::D1Statement::__from_describe(
+                    #sql,
+                    #parameters,
+                    &[#(#columns),*],
+                ));
+        })
+    }
+
     /// ref: <https://github.com/launchbadge/sqlx/blob/1c7b3d0751cdca5a08fbfa7f24c985fc3774cf11/sqlx-macros-core/src/query/args.rs>
     pub(super) fn quote_args_with(
         &self,
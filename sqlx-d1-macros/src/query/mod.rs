@@ -6,7 +6,6 @@ use quote::{format_ident, quote};
 use std::io;
 use std::path::{Path, PathBuf};
 use std::sync::LazyLock;
-use syn::LitStr;
 
 struct Location {
     manifest_dir: PathBuf,
@@ -98,6 +97,14 @@ impl Location {
 }
 
 struct DotSqlx(PathBuf);
+
+/// Content of a query cache file. The SQL is recorded for `sqlx-d1 prepare --check`.
+#[derive(serde::Serialize, serde::Deserialize)]
+struct QueryData<Describe> {
+    query: String,
+    describe: Describe,
+}
+
 impl DotSqlx {
     fn find_in_parent(parent_dir: &Path) -> Result<Option<Self>, io::Error> {
         let candidate = parent_dir.join(".sqlx");
@@ -130,13 +137,25 @@ impl DotSqlx {
     ) -> Result<Option<sqlx_core::describe::Describe<sqlx_d1_core::D1>>, io::Error> {
         match std::fs::read(self.file_path_of(sql)) {
             Ok(bytes) => {
-                let describe = ::serde_json::from_slice(&bytes).map_err(|e| {
+                #[derive(serde::Deserialize)]
+                #[serde(untagged)]
+                enum Cache<Describe> {
+                    QueryData(QueryData<Describe>),
+                    /* cached by older versions */
+                    Describe(Describe),
+                }
+
+                let cache = ::serde_json::from_slice(&bytes).map_err(|e| {
                     io::Error::new(
                         io::ErrorKind::InvalidData,
                         format!("failed to parse the query cache of `{sql}`: {e}"),
                     )
                 })?;
-                Ok(Some(describe))
+                Ok(Some(match cache {
+                    Cache::QueryData(QueryData { describe, .. }) | Cache::Describe(describe) => {
+                        describe
+                    }
+                }))
             }
             Err(e) => {
                 if matches!(e.kind(), io::ErrorKind::NotFound) {
@@ -154,7 +173,11 @@ impl DotSqlx {
         sql: &str,
         describe: sqlx_core::describe::Describe<sqlx_d1_core::D1>,
     ) -> Result<(), io::Error> {
-        let describe = ::serde_json::to_vec(&describe).map_err(|e| {
+        let describe = ::serde_json::to_vec(&QueryData {
+            query: sql.to_owned(),
+            describe,
+        })
+        .map_err(|e| {
             io::Error::new(
                 io::ErrorKind::InvalidData,
                 format!("failed to serialize the query cache of `{sql}`: {e}"),
@@ -167,14 +190,22 @@ impl DotSqlx {
 
 pub(super) fn expand_input(input: TokenStream) -> Result<TokenStream, syn::Error> {
     use sqlx_core::executor::Executor;
-    use sqlx_d1_core::D1Connection;
+    use sqlx_d1_core::D1ConnectOptions;
 
     let input = syn::parse2::<self::input::QueryMacroInput>(input)?;
 
-    let describe = match LOCATION.miniflare_sqlite_file().map_err(|e| syn::Error::new(Span::call_site(), e))? {
+    /* `sqlx-d1 prepare` describes against a temporary database where the migrations are applied */
+    let sqlite_file = match std::env::var_os("SQLX_D1_DATABASE") {
+        Some(path) => Some(PathBuf::from(path)),
+        None => LOCATION
+            .miniflare_sqlite_file()
+            .map_err(|e| syn::Error::new(Span::call_site(), e))?,
+    };
+    let describe = match sqlite_file {
         Some(sqlite_file_path) => {
             futures_lite::future::block_on(async {
-                let mut conn = D1Connection::connect(&format!("sqlite://{}", sqlite_file_path.display()))
+                let mut conn = D1ConnectOptions::sqlite(sqlite_file_path)
+                    .connect()
                     .await
                     .map_err(|e| syn::Error::new(
                         input.src_span,
@@ -204,7 +235,7 @@ pub(super) fn expand_input(input: TokenStream) -> Result<TokenStream, syn::Error
                 .map_err(|e| syn::Error::new(input.src_span, e))?
                 .ok_or_else(|| syn::Error::new(
                     input.src_span,
-                    "there is no cached data for this query, run `cargo sqlx prepare` to update the query cache"
+                    "there is no cached data for this query, run `sqlx-d1 prepare` to update the query cache"
                 ))?,
 
             None => return Err(syn::Error::new(
@@ -214,8 +245,8 @@ pub(super) fn expand_input(input: TokenStream) -> Result<TokenStream, syn::Error
                 `wrangler d1 migrations create <BINDING> <MIGRATION>` and \
                 `wrangler d1 migrations apply <BINDING> --local`.\n\
                 For setting up .sqlx directory for offline mode, \
-                run `cargo sqlx prepare` where `cargo sqlx` is installed and \
-                miniflare D1 emulator is accessable (offen your local PC)."
+                run `sqlx-d1 prepare` where `sqlx-d1` is installed \
+                ( `cargo install sqlx-d1-cli` ) and the migrations are accessable."
             ))
         }
     };
@@ -251,22 +282,30 @@ fn compare_expand(
 
     let query_args_ident = format_ident!("query_args");
 
+    let statement_ident = format_ident!("QUERY_STATEMENT");
+    let statement_tokens = input.quote_statement_with(&statement_ident, &describe)?;
+
     let output = if describe.columns().iter().all({
         use sqlx_core::{column::Column as _, type_info::TypeInfo as _};
         |c| c.type_info().is_void()
     }) {
-        let sql = LitStr::new(&input.sql, input.src_span);
         quote! {
-            ::sqlx_d1::sqlx_core::query::query_with_result::<::sqlx_d1::D1, _>(#sql, #query_args_ident)
+            #statement_ident.__query_with_result(#query_args_ident)
         }
     } else {
         match &input.record_type {
             input::RecordType::Scalar => {
-                output::quote_query_scalar(&input, &query_args_ident, &describe)?
+                output::quote_query_scalar(&input, &statement_ident, &query_args_ident, &describe)?
             }
             input::RecordType::Given(out_ty) => {
                 let columns = output::columns_to_rust(&describe)?;
-                output::quote_query_as(&input, out_ty, &query_args_ident, &columns)
+                output::quote_query_as(
+                    &input,
+                    &statement_ident,
+                    out_ty,
+                    &query_args_ident,
+                    &columns,
+                )
             }
             input::RecordType::Generated => {
                 let columns = self::output::columns_to_rust(&describe)?;
@@ -298,6 +337,7 @@ fn compare_expand(
                 };
                 record_tokens.extend(output::quote_query_as(
                     &input,
+                    &statement_ident,
                     &record_type_name_token,
                     &query_args_ident,
                     &columns,
@@ -308,10 +348,14 @@ fn compare_expand(
         }
     };
 
-    if let Some(dot_sqlx_dir) = LOCATION
-        .dot_sqlx_dir()
-        .map_err(|e| syn::Error::new(input.src_span, e))?
-    {
+    /* `sqlx-d1 prepare` collects the caches into a temporary directory, as sqlx does */
+    let dot_sqlx_dir = match std::env::var_os("SQLX_OFFLINE_DIR") {
+        Some(dir) => Some(DotSqlx(dir.into())),
+        None => LOCATION
+            .dot_sqlx_dir()
+            .map_err(|e| syn::Error::new(input.src_span, e))?,
+    };
+    if let Some(dot_sqlx_dir) = dot_sqlx_dir {
         dot_sqlx_dir
             .cache_describe(&input.sql, describe)
             .map_err(|e| syn::Error::new(input.src_span, e))?;
@@ -325,6 +369,8 @@ fn compare_expand(
 
                 #args_tokens
 
+                #statement_tokens
+
                 #output
             }
         }
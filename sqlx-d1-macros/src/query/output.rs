@@ -133,6 +133,7 @@ fn column_to_rust(describe: &Describe<D1>, i: usize) -> syn::Result<RustColumn>
 
 pub fn quote_query_as(
     input: &QueryMacroInput,
+    statement: &Ident,
     out_ty: &Type,
     bind_args: &Ident,
     columns: &[RustColumn],
@@ -168,16 +169,8 @@ pub fn quote_query_as(
     let ident = columns.iter().map(|col| &col.ident);
     let var_name = columns.iter().map(|col| &col.var_name);
 
-    // if this query came from a file, use `include_str!()` to tell the compiler where it came from
-    let sql = if let Some(path) = &input.file_path {
-        quote::quote_spanned! { input.src_span => include_str!(#path) }
-    } else {
-        let sql = &input.sql;
-        quote! { #sql }
-    };
-
     quote! {
-        ::sqlx_d1::sqlx_core::query::query_with_result::<::sqlx_d1::D1, _>(#sql, #bind_args)
+        #statement.__query_with_result(#bind_args)
             .try_map(|row: <::sqlx_d1::D1 as ::sqlx_d1::sqlx_core::database::Database>::Row| {
                 use ::sqlx_d1::sqlx_core::row::Row as _;
 
@@ -190,6 +183,7 @@ pub fn quote_query_as(
 
 pub fn quote_query_scalar(
     input: &QueryMacroInput,
+    statement: &Ident,
     bind_args: &Ident,
     describe: &Describe<D1>,
 ) -> syn::Result<TokenStream> {
@@ -216,10 +210,8 @@ pub fn quote_query_scalar(
         quote! { _ }
     };
 
-    let sql = &input.sql;
-
     Ok(quote! {
-        ::sqlx_d1::sqlx_core::query_scalar::query_scalar_with_result::<::sqlx_d1::D1, #ty, _>(#sql, #bind_args)
+        #statement.__query_scalar_with_result::<#ty>(#bind_args)
     })
 }
 
@@ -0,0 +1,141 @@
+//! ref: <https://github.com/launchbadge/sqlx/blob/6651d2df72586519708147d96e1ec1054a898c1e/sqlx-macros-core/src/test_attr.rs>
+
+use crate::migrate::{expand_migrator, package_path};
+use proc_macro2::{Span, TokenStream};
+use quote::quote;
+use syn::{ItemFn, LitStr, meta::ParseNestedMeta};
+
+struct Args {
+    /// `None` for `migrations = false`
+    migrations: Option<Migrations>,
+    fixtures: Vec<LitStr>,
+}
+
+enum Migrations {
+    /// `migrations` directory of the package, if it exists
+    Default,
+    Explicit(LitStr),
+}
+
+impl Args {
+    fn parse(args: TokenStream) -> syn::Result<Self> {
+        let mut this = Args {
+            migrations: Some(Migrations::Default),
+            fixtures: Vec::new(),
+        };
+
+        let parser = syn::meta::parser(|meta: ParseNestedMeta| {
+            if meta.path.is_ident("migrations") {
+                match meta.value()?.parse::<syn::Lit>()? {
+                    syn::Lit::Str(dir) => this.migrations = Some(Migrations::Explicit(dir)),
+                    syn::Lit::Bool(b) if !b.value => this.migrations = None,
+                    other => {
+                        return Err(syn::Error::new_spanned(
+                            other,
+                            "expected a directory path or `false`",
+                        ));
+                    }
+                }
+                Ok(())
+            } else if meta.path.is_ident("fixtures") {
+                let content;
+                syn::parenthesized!(content in meta.input);
+                this.fixtures.extend(
+                    content
+                        .parse_terminated(<LitStr as syn::parse::Parse>::parse, syn::Token![,])?,
+                );
+                Ok(())
+            } else {
+                Err(meta.error("expected `migrations` or `fixtures`"))
+            }
+        });
+        syn::parse::Parser::parse2(parser, args)?;
+
+        Ok(this)
+    }
+}
+
+pub(super) fn expand_test(args: TokenStream, input: TokenStream) -> syn::Result<TokenStream> {
+    let args = Args::parse(args)?;
+    let input = syn::parse2::<ItemFn>(input)?;
+
+    if input.sig.asyncness.is_none() {
+        return Err(syn::Error::new_spanned(
+            input.sig.fn_token,
+            "`#[sqlx_d1::test]` requires an `async fn`",
+        ));
+    }
+    if input.sig.inputs.len() != 1 {
+        return Err(syn::Error::new_spanned(
+            &input.sig.inputs,
+            "`#[sqlx_d1::test]` function takes exactly one `D1Connection`",
+        ));
+    }
+
+    let migrator = match &args.migrations {
+        None => None,
+        Some(Migrations::Default) if !package_path("migrations").is_dir() => None,
+        Some(Migrations::Default) => Some(expand_migrator(&LitStr::new(
+            "migrations",
+            Span::call_site(),
+        ))?),
+        Some(Migrations::Explicit(dir)) => Some(expand_migrator(dir)?),
+    }
+    .unwrap_or_else(|| {
+        quote! {
+            ::sqlx_d1::D1Migrator {
+                migrations: ::std::borrow::Cow::Borrowed(&[]),
+                migrations_table: ::std::borrow::Cow::Borrowed("d1_migrations"),
+            }
+        }
+    });
+
+    let fixtures = args.fixtures.iter().map(|fixture| {
+        let name = fixture.value();
+        /* relative to the file of the test like `include_str!`, as sqlx::test does */
+        let path = if name.ends_with(".sql") {
+            name.clone()
+        } else {
+            format!("./fixtures/{name}.sql")
+        };
+        let path = LitStr::new(&path, fixture.span());
+        quote! {
+            ::sqlx_d1::testing::TestScript {
+                path: #name,
+                contents: ::core::include_str!(#path),
+            }
+        }
+    });
+
+    let ItemFn {
+        attrs,
+        vis,
+        sig,
+        block,
+    } = &input;
+    let name = &sig.ident;
+    let output = &sig.output;
+
+    /* the test runs on a native SQLite, not on the Workers runtime */
+    Ok(quote! {
+        #[cfg(target_arch = "wasm32")]
+        ::core::compile_error!(
+            "`#[sqlx_d1::test]` runs on native targets. Run `cargo test` with `--target` of the host."
+        );
+
+        #[cfg(not(target_arch = "wasm32"))]
+        #[::core::prelude::v1::test]
+        #(#attrs)*
+        #vis fn #name() #output {
+            #sig #block
+
+            ::sqlx_d1::testing::run_test(
+                ::sqlx_d1::testing::TestArgs {
+                    migrator: #migrator,
+                    fixtures: &[#(#fixtures),*],
+                },
+                #name,
+            )
+        }
+    })
+}
@@ -10,5 +10,59 @@ pub mod macros;
 #[cfg_attr(docsrs, doc(cfg(feature = "derive")))]
 pub use sqlx_d1_macros::FromRow;
 
+/// `sqlx::test` for Cloudflare D1, running the test with a fresh native SQLite
+/// database where the migrations and the fixtures are applied.
+///
+/// - `migrations = "dir"` : wrangler's migrations directory relative to the package root,
+///   default to `migrations` if it exists. `migrations = false` to skip migrations.
+/// - `fixtures("users", "posts")` : SQL files `fixtures/users.sql`, `fixtures/posts.sql`
+///   relative to the file of the test, applied in the order after migrations.
+///   Names ending with `.sql` are taken as paths relative to the file.
+///
+/// The migrations are applied by [`D1Migrator::run`], so they are recorded in
+/// `d1_migrations` and `D1Migrator::verify` passes in the test.
+///
+/// NOTE: the test runs on native targets only, and fails to compile for `wasm32`.
+/// Run `cargo test --target <host triple>` when `wasm32` is the default target.
+///
+/// NOTE: newly added migration files are embedded when the test is recompiled.
+///
+/// ## Example
+///
+/// ```rust,ignore
+/// #[sqlx_d1::test(fixtures("users"))]
+/// async fn test_rename_user(conn: sqlx_d1::D1Connection) {
+///     sqlx_d1::query!("UPDATE users SET name = ? WHERE id = ?", "bob", 1)
+///         .execute(&conn)
+///         .await
+///         .unwrap();
+/// }
+/// ```
+#[cfg(feature = "test")]
+#[cfg_attr(docsrs, doc(cfg(feature = "test")))]
+pub use sqlx_d1_macros::test;
+
+/// Embed wrangler's migrations directory relative to the package root
+/// ( default to `migrations` ) as [`D1Migrator`].
+///
+/// Migration files are `NNNN_name.sql` made by `wrangler d1 migrations create`,
+/// and applied in the order of the names. Applied ones are recorded in the
+/// `d1_migrations` table as `wrangler d1 migrations apply` does, so the two
+/// are interchangeable. For `migrations_table` set in wrangler's config, use
+/// `MIGRATOR.clone().migrations_table("...")`.
+///
+/// NOTE: newly added migration files are embedded when the crate is recompiled.
+///
+/// ## Example
+///
+/// ```rust,ignore
+/// static MIGRATOR: sqlx_d1::D1Migrator = sqlx_d1::migrate!("migrations");
+///
+/// let applied = MIGRATOR.run(&conn).await?;
+/// ```
+#[cfg(feature = "migrate")]
+#[cfg_attr(docsrs, doc(cfg(feature = "migrate")))]
+pub use sqlx_d1_macros::migrate;
+
 pub use sqlx_d1_core::sqlx_core::from_row::FromRow;
 pub use sqlx_d1_core::*;
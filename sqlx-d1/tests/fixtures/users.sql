@@ -0,0 +1,2 @@
+INSERT INTO users (id, name) VALUES (1, 'alice');
+INSERT INTO users (id, name) VALUES (2, 'bob');
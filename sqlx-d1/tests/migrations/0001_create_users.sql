@@ -0,0 +1,5 @@
+-- Migration number: 0001
+CREATE TABLE users (
+    id   INTEGER PRIMARY KEY,
+    name TEXT NOT NULL
+);
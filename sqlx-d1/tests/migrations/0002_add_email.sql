@@ -0,0 +1,2 @@
+-- Migration number: 0002
+ALTER TABLE users ADD COLUMN email TEXT;
@@ -0,0 +1,38 @@
+#![cfg(not(target_arch = "wasm32"))]
+
+use sqlx_d1::D1Connection;
+use sqlx_d1::query::query_scalar;
+
+static MIGRATOR: sqlx_d1::D1Migrator = sqlx_d1::migrate!("tests/migrations");
+
+#[sqlx_d1::test(migrations = "tests/migrations", fixtures("users"))]
+async fn migrations_and_fixtures_are_applied(conn: D1Connection) {
+    let names = query_scalar::<String>("SELECT name FROM users ORDER BY id")
+        .fetch_all(&conn)
+        .await
+        .unwrap();
+    assert_eq!(names, ["alice", "bob"]);
+
+    let emails = query_scalar::<Option<String>>("SELECT email FROM users")
+        .fetch_all(&conn)
+        .await
+        .unwrap();
+    assert!(emails.iter().all(Option::is_none));
+}
+
+#[sqlx_d1::test(migrations = "tests/migrations")]
+async fn migrations_are_recorded(conn: D1Connection) {
+    MIGRATOR.verify(&conn).await.unwrap();
+    assert!(MIGRATOR.run(&conn).await.unwrap().is_empty());
+}
+
+#[sqlx_d1::test(migrations = false, fixtures("./fixtures/empty.sql"))]
+async fn migrations_can_be_skipped(conn: D1Connection) {
+    let tables = query_scalar::<String>(
+        "SELECT name FROM sqlite_master WHERE type = 'table' AND name = 'users'",
+    )
+    .fetch_all(&conn)
+    .await
+    .unwrap();
+    assert!(tables.is_empty());
+}